@@ -17,12 +17,16 @@
 //! Triangles:
 //!     Quick and easy way of filling in panels on a craft to give it a smoother design
 //!
+//! Hinges:
+//!     Rotating joints between two sub-structures which share a single pivot node.
+//!     A hinge can limit how far it turns, and can carry a motor driven by a control channel
+//!
 use macroquad::prelude::Vec2;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 #[serde(remote = "Vec2")]
-struct Vec2Def {
+pub(crate) struct Vec2Def {
     x: f32,
     y: f32,
 }
@@ -34,6 +38,9 @@ pub struct Node {
     pub pos: Vec2,
     #[serde(with = "Vec2Def")]
     pub prev_pos: Vec2,
+    /// Parts sharing a non-zero collision group pass through each other
+    #[serde(default)]
+    pub collision_group: u8,
 }
 
 /* Represents a connection between nodes */
@@ -43,17 +50,97 @@ pub struct Rod {
     pub node_b: usize,
     pub length: f32,
     pub rod_type: RodType,
+    #[serde(default)]
+    pub collision_group: u8,
+    /// Ropes only, number of links the rope is simulated as, below 2 it is a single link
+    #[serde(default)]
+    pub segments: usize,
+    /// Ropes only, reels the rope in and out
+    #[serde(default)]
+    pub winch: Option<Winch>,
 }
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
 pub enum RodType {
+    #[default]
     SOLID,
     ROPE,
     SPRING,
     PISTON,
 }
-impl Default for RodType {
+
+/* Represents a rotating joint, each arm node belongs to one of the two sub-structures */
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Hinge {
+    pub pivot: usize,
+    pub arm_a: usize,
+    pub arm_b: usize,
+    /// Smallest and largest allowed angle from arm a to arm b, in radians within `-PI..=PI`
+    #[serde(default)]
+    pub limits: Option<(f32, f32)>,
+    #[serde(default)]
+    pub motor: Option<Motor>,
+}
+impl Hinge {
+    pub fn nodes(&self) -> [usize; 3] {
+        [self.pivot, self.arm_a, self.arm_b]
+    }
+    pub fn uses(&self, node_id: usize) -> bool {
+        self.nodes().contains(&node_id)
+    }
+    /// Points the hinge at new node indices, used when nodes are removed or merged
+    pub fn remap(&mut self, f: impl Fn(usize) -> usize) {
+        self.pivot = f(self.pivot);
+        self.arm_a = f(self.arm_a);
+        self.arm_b = f(self.arm_b);
+    }
+}
+
+/* Drives a hinge towards a turning speed set by a control channel */
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Motor {
+    pub channel: usize,
+    /// Turning speed at full input, in radians per second
+    pub speed: f32,
+    /// Largest change in turning speed per second, in radians per second squared
+    pub strength: f32,
+}
+impl Default for Motor {
     fn default() -> Self {
-        Self::SOLID
+        Self {
+            channel: 0,
+            speed: 3.0,
+            strength: 40.0,
+        }
+    }
+}
+
+/* Changes the length of a rope at a rate set by a control channel */
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Winch {
+    pub channel: usize,
+    /// Change in rope length at full input, in pixels per second, positive input reels out
+    pub speed: f32,
+    pub min_length: f32,
+    pub max_length: f32,
+    /// Strength of the winch, the fastest it can accelerate the pulled node while reeling in,
+    /// in pixels per second squared. Node masses aren't simulated, so this stands in for a force
+    #[serde(alias = "max_force")]
+    pub max_acceleration: f32,
+}
+impl Winch {
+    const MIN_LENGTH: f32 = 20.0;
+    /// How far past its length a new winch can reel a rope out, as a multiple of the length
+    const EXTENSION: f32 = 2.0;
+
+    /// Creates a winch which can reel a rope of the given length in or out to twice as long
+    pub fn new(length: f32) -> Self {
+        Self {
+            channel: 1,
+            speed: 60.0,
+            min_length: Self::MIN_LENGTH.min(length),
+            max_length: length * Self::EXTENSION,
+            max_acceleration: 2000.0,
+        }
     }
 }
 
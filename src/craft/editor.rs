@@ -5,73 +5,199 @@
 //! carry out edits on a craft
 //!
 //! A craft is a raw structure and has no checks on its own state
+//!
+//! The manager keeps a spatial index of the craft's nodes and rods so picking, box selection
+//! and deduplication only look at nearby components. Any edit made directly to the craft must
+//! be followed by a call to `rebuild_index`.
+//!
+//! Edits made through the manager are counted, so the editor can tell whether the craft has
+//! changed since it was last saved. Direct edits should call `mark_edited` to be counted too.
 
 use super::{
     Craft,
-    components::{Node, Rod, RodType},
+    components::{Hinge, Motor, Node, Rod, RodType},
+    geometry::*,
+    spatial::SpatialGrid,
 };
 use macroquad::prelude::Vec2;
 use std::collections::HashSet;
 
+/// How rods are matched against a selection box or lasso
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectMode {
+    /// The whole rod must lie inside the selection
+    Contained,
+    /// Any part of the rod touching the selection is enough
+    Intersecting,
+}
+
 pub struct CraftManager {
     pub c: Craft,
+    node_index: SpatialGrid,
+    rod_index: SpatialGrid,
+    /// Number of edits made to the craft
+    revision: u64,
+    /// Revision the craft was at when last saved
+    saved_revision: u64,
 }
 impl CraftManager {
     const THRESHOLD: f32 = 50.0;
+    const CELL_SIZE: f32 = 64.0;
+
+    pub fn new(craft: Craft) -> Self {
+        let mut manager = Self {
+            c: craft,
+            node_index: SpatialGrid::new(Self::CELL_SIZE),
+            rod_index: SpatialGrid::new(Self::CELL_SIZE),
+            revision: 0,
+            saved_revision: 0,
+        };
+        manager.rebuild_index();
+        manager
+    }
+
+    /// Replaces the craft being edited
+    pub fn set_craft(&mut self, craft: Craft) {
+        self.c = craft;
+        self.rebuild_index();
+        self.mark_edited();
+    }
+
+    /* Unsaved changes */
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+    /// Counts an edit made directly to the craft
+    pub fn mark_edited(&mut self) {
+        self.revision += 1;
+    }
+    pub fn mark_saved(&mut self) {
+        self.saved_revision = self.revision;
+    }
+    /// Whether the craft has been edited since it was last saved
+    pub fn is_dirty(&self) -> bool {
+        self.revision != self.saved_revision
+    }
+
+    /* Spatial index */
+    /// Rebuilds the spatial index from scratch, needed after components are moved or removed
+    pub fn rebuild_index(&mut self) {
+        self.node_index.clear();
+        self.rod_index.clear();
+        for i in 0..self.c.nodes.len() {
+            self.index_node(i);
+        }
+        for i in 0..self.c.rods.len() {
+            self.index_rod(i);
+        }
+    }
+    fn index_node(&mut self, node_id: usize) {
+        self.node_index
+            .insert_point(node_id, self.c.nodes[node_id].pos);
+    }
+    fn index_rod(&mut self, rod_id: usize) {
+        let rod = &self.c.rods[rod_id];
+        let (a, b) = (self.c.nodes[rod.node_a].pos, self.c.nodes[rod.node_b].pos);
+        self.rod_index.insert_segment(rod_id, a, b);
+    }
 
     /* Selecting craft components */
-    pub fn select_nearest_node(&self, pos: Vec2) -> Option<usize> {
-        self.c
-            .nodes
-            .iter()
-            .enumerate()
-            .filter_map(|(i, node)| {
-                let dist = node.pos.distance_squared(pos);
-                if dist < Self::THRESHOLD {
-                    Some((i, dist))
-                } else {
-                    None
-                }
-            })
+    /// Closest node within `radius` of `pos`, with its squared distance
+    pub fn select_nearest_node(&self, pos: Vec2, radius: f32) -> Option<(usize, f32)> {
+        self.node_index
+            .query_radius(pos, radius)
+            .into_iter()
+            .map(|i| (i, self.c.nodes[i].pos.distance_squared(pos)))
+            .filter(|(_, dist)| *dist < radius * radius)
             .min_by(|a, b| a.1.total_cmp(&b.1))
-            .map(|(i, _)| i)
     }
-    pub fn select_nearest_rod(&self, pos: Vec2) -> Option<usize> {
-        self.c
-            .rods
-            .iter()
-            .enumerate()
-            .filter_map(|(i, rod)| {
-                let a = self.c.nodes[rod.node_a].pos;
-                let b = self.c.nodes[rod.node_b].pos;
-                let midpoint = (a + b) * 0.5;
-                let dist = midpoint.distance_squared(pos);
-                if dist < Self::THRESHOLD * Self::THRESHOLD {
-                    Some((i, dist))
-                } else {
-                    None
-                }
+    /// Closest rod within `radius` of `pos`, measured to the nearest point along the rod,
+    /// with its squared distance
+    pub fn select_nearest_rod(&self, pos: Vec2, radius: f32) -> Option<(usize, f32)> {
+        self.rod_index
+            .query_radius(pos, radius)
+            .into_iter()
+            .map(|i| (i, self.rod_distance_squared(i, pos)))
+            .filter(|(_, dist)| *dist < radius * radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+    pub fn rod_distance_squared(&self, rod_id: usize, pos: Vec2) -> f32 {
+        let (a, b) = self.rod_ends(rod_id);
+        point_segment_distance_squared(pos, a, b)
+    }
+    fn rod_ends(&self, rod_id: usize) -> (Vec2, Vec2) {
+        let rod = &self.c.rods[rod_id];
+        (self.c.nodes[rod.node_a].pos, self.c.nodes[rod.node_b].pos)
+    }
+
+    /* Area selection */
+    /// Nodes lying inside the box
+    pub fn nodes_in_box(&self, start: Vec2, end: Vec2) -> Vec<usize> {
+        let (min, max) = (start.min(end), start.max(end));
+        self.node_index
+            .query(min, max)
+            .into_iter()
+            .filter(|&i| {
+                let pos = self.c.nodes[i].pos;
+                pos.cmpge(min).all() && pos.cmple(max).all()
             })
-            .min_by(|a, b| {
-                a.1.total_cmp(&b.1)
+            .collect()
+    }
+    /// Rods inside or touching the box depending on the mode
+    pub fn rods_in_box(&self, start: Vec2, end: Vec2, mode: SelectMode) -> Vec<usize> {
+        self.rods_in_polygon(&box_polygon(start, end), mode)
+    }
+    /// Nodes lying inside a closed polygon, such as a lasso
+    pub fn nodes_in_polygon(&self, polygon: &[Vec2]) -> Vec<usize> {
+        let Some((min, max)) = bounds(polygon) else {
+            return vec![];
+        };
+        self.node_index
+            .query(min, max)
+            .into_iter()
+            .filter(|&i| point_in_polygon(self.c.nodes[i].pos, polygon))
+            .collect()
+    }
+    /// Rods inside or touching a closed polygon depending on the mode
+    pub fn rods_in_polygon(&self, polygon: &[Vec2], mode: SelectMode) -> Vec<usize> {
+        let Some((min, max)) = bounds(polygon) else {
+            return vec![];
+        };
+        self.rod_index
+            .query(min, max)
+            .into_iter()
+            .filter(|&i| {
+                let (a, b) = self.rod_ends(i);
+                let crosses = segment_crosses_polygon(a, b, polygon);
+                match mode {
+                    SelectMode::Contained => {
+                        !crosses && point_in_polygon(a, polygon) && point_in_polygon(b, polygon)
+                    }
+                    SelectMode::Intersecting => {
+                        crosses || point_in_polygon(a, polygon) || point_in_polygon(b, polygon)
+                    }
+                }
             })
-            .map(|(i, _)| i)
+            .collect()
     }
 
     /* Removing duplicates */
     pub fn remove_duplicate_nodes(&mut self) {
-        let mut unique = vec![];
+        let mut unique: Vec<Node> = vec![];
+        let mut unique_index = SpatialGrid::new(Self::THRESHOLD);
         let mut mapping = vec![None; self.c.nodes.len()];
 
         for (i, node) in self.c.nodes.iter().enumerate() {
-            if let Some((idx, _)) = unique
-                .iter()
-                .enumerate()
-                .find(|(_, n): &(_, &Node)| (n.pos - node.pos).length() < Self::THRESHOLD)
+            // Candidates are sorted, so the first match is the earliest unique node
+            if let Some(idx) = unique_index
+                .query_radius(node.pos, Self::THRESHOLD)
+                .into_iter()
+                .find(|&idx| (unique[idx].pos - node.pos).length() < Self::THRESHOLD)
             {
                 mapping[i] = Some(idx);
             } else {
                 mapping[i] = Some(unique.len());
+                unique_index.insert_point(unique.len(), node.pos);
                 unique.push(node.clone());
             }
         }
@@ -82,12 +208,46 @@ impl CraftManager {
             rod.node_a = mapping[rod.node_a].unwrap();
             rod.node_b = mapping[rod.node_b].unwrap();
         }
+        for hinge in &mut self.c.hinges {
+            hinge.remap(|id| mapping[id].unwrap());
+        }
+        // Hinges whose nodes were merged together no longer make sense
+        self.c
+            .hinges
+            .retain(|h| h.pivot != h.arm_a && h.pivot != h.arm_b && h.arm_a != h.arm_b);
+        self.rebuild_index();
+        self.mark_edited();
     }
     pub fn remove_duplicate_rods(&mut self) {
         let mut seen = HashSet::new();
         self.c
             .rods
             .retain(|rod| seen.insert([rod.node_a.min(rod.node_b), rod.node_a.max(rod.node_b)]));
+        self.rebuild_index();
+        self.mark_edited();
+    }
+
+    /* Collision groups */
+    /// Puts the given nodes and rods into a collision group of their own,
+    /// so they no longer collide with each other, `None` if every group is already in use
+    pub fn group_components(&mut self, node_ids: &[usize], rod_ids: &[usize]) -> Option<u8> {
+        let used: HashSet<u8> = self
+            .c
+            .nodes
+            .iter()
+            .map(|n| n.collision_group)
+            .chain(self.c.rods.iter().map(|r| r.collision_group))
+            .collect();
+        let group = (1..=u8::MAX).find(|g| !used.contains(g))?;
+
+        for &id in node_ids {
+            self.c.nodes[id].collision_group = group;
+        }
+        for &id in rod_ids {
+            self.c.rods[id].collision_group = group;
+        }
+        self.mark_edited();
+        Some(group)
     }
 
     /* Midpoint calculations */
@@ -107,7 +267,10 @@ impl CraftManager {
         self.c.nodes.push(Node {
             pos,
             prev_pos: pos,
+            collision_group: 0,
         });
+        self.index_node(self.c.nodes.len() - 1);
+        self.mark_edited();
         self.c.nodes.len() - 1
     }
     pub fn add_rod(&mut self, node1: usize, node2: usize, rod_type: RodType) -> usize {
@@ -116,9 +279,32 @@ impl CraftManager {
             node_b: node2,
             length: (self.c.nodes[node1].pos - self.c.nodes[node2].pos).length(),
             rod_type,
+            collision_group: 0,
+            segments: 0,
+            winch: None,
         });
+        self.index_rod(self.c.rods.len() - 1);
+        self.mark_edited();
         self.c.rods.len() - 1
     }
+    /// Adds a hinge turning about `pivot`, the arms must be on opposite sub-structures
+    pub fn add_hinge(
+        &mut self,
+        arm_a: usize,
+        pivot: usize,
+        arm_b: usize,
+        motor: Option<Motor>,
+    ) -> usize {
+        self.c.hinges.push(Hinge {
+            pivot,
+            arm_a,
+            arm_b,
+            limits: None,
+            motor,
+        });
+        self.mark_edited();
+        self.c.hinges.len() - 1
+    }
 
     /* Removing components from craft */
     pub fn remove_node(&mut self, node_id: usize) -> Option<()> {
@@ -126,14 +312,15 @@ impl CraftManager {
             return None;
         }
 
-        // Remove all rods and triangles connected to this node
+        // Remove all rods, hinges and triangles connected to this node
         self.c
             .rods
             .retain(|rod| rod.node_a != node_id && rod.node_b != node_id);
+        self.c.hinges.retain(|hinge| !hinge.uses(node_id));
 
         self.c.nodes.remove(node_id);
 
-        // Fix indices in rods and triangles
+        // Fix indices in rods, hinges and triangles
         for rod in &mut self.c.rods {
             if rod.node_a > node_id {
                 rod.node_a -= 1;
@@ -142,6 +329,11 @@ impl CraftManager {
                 rod.node_b -= 1;
             }
         }
+        for hinge in &mut self.c.hinges {
+            hinge.remap(|id| if id > node_id { id - 1 } else { id });
+        }
+        self.rebuild_index();
+        self.mark_edited();
 
         Some(())
     }
@@ -149,6 +341,8 @@ impl CraftManager {
         if rod_id <= self.c.rods.len() {
             self.c.rods[rod_id] = self.c.rods[self.c.rods.len() - 1].clone();
             self.c.rods.remove(rod_id);
+            self.rebuild_index();
+            self.mark_edited();
             Some(())
         } else {
             None
@@ -170,10 +364,11 @@ impl CraftManager {
                 continue;
             }
 
-            // Remove rods connected to this node
+            // Remove rods and hinges connected to this node
             self.c
                 .rods
                 .retain(|rod| rod.node_a != id && rod.node_b != id);
+            self.c.hinges.retain(|hinge| !hinge.uses(id));
 
             if id != last {
                 // Swap node
@@ -188,6 +383,9 @@ impl CraftManager {
                         rod.node_b = id;
                     }
                 }
+                for hinge in &mut self.c.hinges {
+                    hinge.remap(|n| if n == last { id } else { n });
+                }
 
                 // Update any upcoming deletions for the last index
                 if let Some(pos) = node_ids.iter().position(|&x| x == last) {
@@ -198,6 +396,8 @@ impl CraftManager {
             self.c.nodes.pop();
             i += 1;
         }
+        self.rebuild_index();
+        self.mark_edited();
     }
     pub fn remove_rods(&mut self, rod_ids: &[usize]) {
         let mut rod_ids = rod_ids.to_vec();
@@ -225,7 +425,52 @@ impl CraftManager {
             self.c.rods.pop();
             i += 1;
         }
+        self.rebuild_index();
+        self.mark_edited();
     }
 
     /* Chain placement */
 }
+
+/// Bounding box of a set of points
+fn bounds(points: &[Vec2]) -> Option<(Vec2, Vec2)> {
+    let first = *points.first()?;
+    Some(
+        points
+            .iter()
+            .fold((first, first), |(min, max), &p| (min.min(p), max.max(p))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::vec2;
+
+    #[test]
+    fn each_group_gets_a_new_number() {
+        let mut manager = CraftManager::new(Craft::new());
+        let a = manager.add_node(vec2(0.0, 0.0));
+        let b = manager.add_node(vec2(10.0, 0.0));
+        let rod = manager.add_rod(a, b, RodType::SOLID);
+
+        assert_eq!(manager.group_components(&[a], &[rod]), Some(1));
+        assert_eq!(manager.group_components(&[b], &[]), Some(2));
+        assert_eq!(manager.c.nodes[a].collision_group, 1);
+        assert_eq!(manager.c.rods[rod].collision_group, 1);
+        assert!(manager.is_dirty());
+    }
+
+    #[test]
+    fn grouping_fails_once_groups_run_out() {
+        let mut manager = CraftManager::new(Craft::new());
+        for i in 0..=u8::MAX as usize {
+            let id = manager.add_node(vec2(i as f32, 0.0));
+            if i > 0 {
+                assert!(manager.group_components(&[id], &[]).is_some());
+            }
+        }
+        assert_eq!(manager.group_components(&[0], &[]), None);
+        assert_eq!(manager.c.nodes[0].collision_group, 0);
+    }
+}
@@ -0,0 +1,135 @@
+//! Vehicle Toolkit
+//!
+//! Small 2D geometry helpers shared by picking and selection
+use macroquad::prelude::Vec2;
+
+/// Squared distance from `p` to the closest point on the segment `a`-`b`
+pub fn point_segment_distance_squared(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let len_sq = ab.length_squared();
+    if len_sq == 0.0 {
+        return p.distance_squared(a);
+    }
+    let t = ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+    p.distance_squared(a + ab * t)
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` cross or touch
+pub fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(c - a);
+    let d2 = (b - a).perp_dot(d - a);
+    let d3 = (d - c).perp_dot(a - c);
+    let d4 = (d - c).perp_dot(b - c);
+
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    // Collinear end points touching the other segment
+    let on_segment = |p: Vec2, a: Vec2, b: Vec2| p.cmpge(a.min(b)).all() && p.cmple(a.max(b)).all();
+    (d1 == 0.0 && on_segment(c, a, b))
+        || (d2 == 0.0 && on_segment(d, a, b))
+        || (d3 == 0.0 && on_segment(a, c, d))
+        || (d4 == 0.0 && on_segment(b, c, d))
+}
+
+/// Even-odd test for a point inside a closed polygon
+pub fn point_in_polygon(p: Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Whether the segment `a`-`b` crosses any edge of a closed polygon
+pub fn segment_crosses_polygon(a: Vec2, b: Vec2, polygon: &[Vec2]) -> bool {
+    (0..polygon.len()).any(|i| {
+        let (c, d) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        segments_intersect(a, b, c, d)
+    })
+}
+
+/// Corners of the axis aligned box spanned by two points
+pub fn box_polygon(start: Vec2, end: Vec2) -> [Vec2; 4] {
+    let (min, max) = (start.min(end), start.max(end));
+    [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+}
+
+/// Area enclosed by a polygon, whatever its winding
+pub fn polygon_area(polygon: &[Vec2]) -> f32 {
+    let mut twice_area = 0.0;
+    for i in 0..polygon.len() {
+        twice_area += polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]);
+    }
+    twice_area.abs() * 0.5
+}
+
+/// Part of a polygon inside the box between `min` and `max`, may be empty
+pub fn clip_polygon_to_box(polygon: &[Vec2], min: Vec2, max: Vec2) -> Vec<Vec2> {
+    let mut clipped = polygon.to_vec();
+    // Each edge of the box as an axis, a bound, and whether points must be below it
+    for (axis, bound, below) in [
+        (0, min.x, false),
+        (0, max.x, true),
+        (1, min.y, false),
+        (1, max.y, true),
+    ] {
+        let inside = |p: Vec2| (p[axis] <= bound) == below || p[axis] == bound;
+        let input = std::mem::take(&mut clipped);
+        for i in 0..input.len() {
+            let (a, b) = (input[i], input[(i + 1) % input.len()]);
+            if inside(a) {
+                clipped.push(a);
+            }
+            if inside(a) != inside(b) {
+                let t = (bound - a[axis]) / (b[axis] - a[axis]);
+                clipped.push(a.lerp(b, t));
+            }
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::vec2;
+
+    fn square(min: Vec2, max: Vec2) -> Vec<Vec2> {
+        box_polygon(min, max).to_vec()
+    }
+
+    #[test]
+    fn area_ignores_winding() {
+        let mut polygon = square(Vec2::ZERO, vec2(2.0, 3.0));
+        assert_eq!(polygon_area(&polygon), 6.0);
+        polygon.reverse();
+        assert_eq!(polygon_area(&polygon), 6.0);
+    }
+
+    #[test]
+    fn polygons_inside_the_box_are_kept() {
+        let polygon = square(vec2(1.0, 1.0), vec2(2.0, 2.0));
+        let clipped = clip_polygon_to_box(&polygon, Vec2::ZERO, vec2(5.0, 5.0));
+        assert_eq!(clipped, polygon);
+    }
+
+    #[test]
+    fn polygons_outside_the_box_are_removed() {
+        let polygon = square(vec2(10.0, 10.0), vec2(12.0, 12.0));
+        assert!(clip_polygon_to_box(&polygon, Vec2::ZERO, vec2(5.0, 5.0)).is_empty());
+    }
+
+    #[test]
+    fn polygons_crossing_the_box_are_cut() {
+        let polygon = square(Vec2::ZERO, vec2(2.0, 2.0));
+        let clipped = clip_polygon_to_box(&polygon, vec2(1.0, -1.0), vec2(3.0, 3.0));
+        assert_eq!(polygon_area(&clipped), 2.0);
+        assert!(clipped.iter().all(|p| p.x >= 1.0));
+    }
+}
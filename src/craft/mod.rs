@@ -7,6 +7,7 @@
 //! A `Craft` is composed of:
 //! - **Nodes**: connection points that define locations in 2D space.
 //! - **Rods**: links between nodes that simulate physical constraints (like solid bars, springs, ropes, etc.).
+//! - **Hinges**: rotating joints between sub-structures, optionally limited or motorised.
 //!
 //! The module supports loading/saving a craft from disk and rendering it visually.
 use std::fs::File;
@@ -16,16 +17,27 @@ use std::path::PathBuf;
 use macroquad::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::physics::{ropes::slack_points, settings::PhysicsSettings};
+use crate::style::palette;
+
 pub mod components;
 pub mod editor;
+pub mod geometry;
+pub mod rigidity;
+pub mod spatial;
 use components::*;
 
 /// Represents a physical structure made of nodes connected by rods.
 /// Nodes are points in 2D space, and rods define physical constraints between them.
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct Craft {
     pub nodes: Vec<Node>,
     pub rods: Vec<Rod>,
+    #[serde(default)]
+    pub hinges: Vec<Hinge>,
+    /// Physics the craft is meant to be tested with, the defaults are used when not set
+    #[serde(default)]
+    pub physics: Option<PhysicsSettings>,
 }
 impl Craft {
     /// Creates a new, empty craft.
@@ -33,7 +45,17 @@ impl Craft {
         Self {
             nodes: vec![],
             rods: vec![],
+            hinges: vec![],
+            physics: None,
+        }
+    }
+
+    /// Average position of the craft's nodes
+    pub fn centre(&self) -> Vec2 {
+        if self.nodes.is_empty() {
+            return Vec2::ZERO;
         }
+        self.nodes.iter().map(|n| n.pos).sum::<Vec2>() / self.nodes.len() as f32
     }
 }
 impl Craft {
@@ -50,18 +72,37 @@ impl Craft {
 
     /// Saves craft into JSON file
     pub fn save(&self, file_path: PathBuf) -> io::Result<()> {
-        let file = File::open(file_path)?;
-        serde_json::to_writer(file, self)
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Error saving Craft"))?;
+        let file = File::create(file_path)?;
+        serde_json::to_writer(file, self).map_err(|_| io::Error::other("Error saving Craft"))?;
         io::Result::Ok(())
     }
 }
 
+/// Draws a rope, hanging loose when its ends are closer than its length
+fn draw_rope(a: Vec2, b: Vec2, length: f32) {
+    let points = slack_points(a, b, length, 12);
+    for pair in points.windows(2) {
+        draw_line(
+            pair[0].x,
+            pair[0].y,
+            pair[1].x,
+            pair[1].y,
+            2.0,
+            palette().rope,
+        );
+    }
+}
+
 /// Temporary, will find a better way to structure project in the future
 pub fn draw_craft(craft: &Craft) {
+    let palette = palette();
     // Draw rods
     for rod in &craft.rods {
         let (a, b) = (rod.node_a, rod.node_b);
+        if rod.rod_type == RodType::ROPE {
+            draw_rope(craft.nodes[a].pos, craft.nodes[b].pos, rod.length);
+            continue;
+        }
         draw_line(
             craft.nodes[a].pos.x,
             craft.nodes[a].pos.y,
@@ -69,16 +110,33 @@ pub fn draw_craft(craft: &Craft) {
             craft.nodes[b].pos.y,
             2.0,
             match rod.rod_type {
-                RodType::SOLID { .. } => DARKGRAY,
-                RodType::ROPE { .. } => RED,
-                RodType::SPRING {} => BLUE,
-                RodType::PISTON { .. } => ORANGE,
+                RodType::SOLID => palette.solid,
+                RodType::ROPE => palette.rope,
+                RodType::SPRING => palette.spring,
+                RodType::PISTON => palette.piston,
             },
         );
     }
 
     // Draw nodes
     for node in &craft.nodes {
-        draw_circle(node.pos.x, node.pos.y, 6.0, BLACK);
+        draw_circle(node.pos.x, node.pos.y, 6.0, palette.node);
+    }
+
+    // Draw winches at the start of their rope
+    for rod in craft.rods.iter().filter(|rod| rod.winch.is_some()) {
+        let pos = craft.nodes[rod.node_a].pos;
+        draw_rectangle_lines(pos.x - 9.0, pos.y - 9.0, 18.0, 18.0, 2.0, palette.winch);
+    }
+
+    // Draw hinges around their pivot
+    for hinge in &craft.hinges {
+        let pivot = craft.nodes[hinge.pivot].pos;
+        let color = if hinge.motor.is_some() {
+            palette.winch
+        } else {
+            palette.hinge
+        };
+        draw_circle_lines(pivot.x, pivot.y, 10.0, 2.0, color);
     }
 }
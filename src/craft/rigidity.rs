@@ -0,0 +1,428 @@
+//! Vehicle Toolkit
+//!
+//! This module checks whether a craft is properly braced.
+//!
+//! Only `RodType::SOLID` rods are treated as bars, every other rod type is free to change length
+//! and so adds no rigidity. The analysis runs the 2D pebble game (Jacobs & Hendrickson) which
+//! counts independent bars in `O(n * m)`, giving:
+//!     - The internal degrees of freedom of every connected component
+//!     - The rigid clusters inside each component, which move as a single body
+//!     - A set of bracing rods that would remove the remaining degrees of freedom
+//!
+//! A component with one or more degrees of freedom is a mechanism, it will fold or flop
+//! in the simulation unless it is braced.
+//!
+//! Braces are first looked for between each node and its nearest neighbours, which is nearly
+//! always enough. Every pair in the component is only tried if that fails and the component
+//! is small, so large crafts don't stall the editor each time they are edited.
+
+use super::{Craft, components::RodType};
+
+/// A group of nodes joined together by solid rods
+pub struct Component {
+    pub nodes: Vec<usize>,
+    pub rods: Vec<usize>,
+    /// Degrees of freedom left once the rigid body motions are removed, `0` means rigid
+    pub dof: usize,
+    /// Sets of rods that move together as a single rigid body
+    pub clusters: Vec<Vec<usize>>,
+}
+impl Component {
+    pub fn is_rigid(&self) -> bool {
+        self.dof == 0
+    }
+}
+
+/// Result of running a rigidity analysis on a craft
+pub struct RigidityReport {
+    pub components: Vec<Component>,
+    /// Node pairs which, when joined with solid rods, make each component rigid
+    pub suggested_braces: Vec<(usize, usize)>,
+    mechanism_nodes: Vec<bool>,
+    mechanism_rods: Vec<bool>,
+}
+impl RigidityReport {
+    /// Runs the rigidity analysis on a craft
+    pub fn analyse(craft: &Craft) -> Self {
+        let solid_rods: Vec<usize> = craft
+            .rods
+            .iter()
+            .enumerate()
+            .filter(|(_, rod)| rod.rod_type == RodType::SOLID && rod.node_a != rod.node_b)
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut game = PebbleGame::new(craft.nodes.len());
+        for &i in &solid_rods {
+            game.insert(craft.rods[i].node_a, craft.rods[i].node_b);
+        }
+
+        let mut components = group_components(craft, &solid_rods);
+        let mut suggested_braces = vec![];
+        for component in &mut components {
+            component.dof = game.dof(&component.nodes);
+            component.clusters = game.rigid_clusters(craft, &component.rods);
+            if !component.is_rigid() {
+                suggested_braces.extend(game.brace(craft, component));
+            }
+        }
+
+        let mut mechanism_nodes = vec![false; craft.nodes.len()];
+        let mut mechanism_rods = vec![false; craft.rods.len()];
+        for component in components.iter().filter(|c| !c.is_rigid()) {
+            component
+                .nodes
+                .iter()
+                .for_each(|&n| mechanism_nodes[n] = true);
+            component
+                .rods
+                .iter()
+                .for_each(|&r| mechanism_rods[r] = true);
+        }
+
+        Self {
+            components,
+            suggested_braces,
+            mechanism_nodes,
+            mechanism_rods,
+        }
+    }
+
+    /// Total degrees of freedom across every component
+    pub fn total_dof(&self) -> usize {
+        self.components.iter().map(|c| c.dof).sum()
+    }
+    pub fn is_rigid(&self) -> bool {
+        self.total_dof() == 0
+    }
+
+    /// Whether a node belongs to a component which is not rigid
+    pub fn is_mechanism_node(&self, node_id: usize) -> bool {
+        self.mechanism_nodes.get(node_id).copied().unwrap_or(false)
+    }
+    /// Whether a rod belongs to a component which is not rigid
+    pub fn is_mechanism_rod(&self, rod_id: usize) -> bool {
+        self.mechanism_rods.get(rod_id).copied().unwrap_or(false)
+    }
+}
+
+/// Splits the craft into groups of nodes connected by the given rods
+fn group_components(craft: &Craft, rods: &[usize]) -> Vec<Component> {
+    let mut parent: Vec<usize> = (0..craft.nodes.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for &i in rods {
+        let a = find(&mut parent, craft.rods[i].node_a);
+        let b = find(&mut parent, craft.rods[i].node_b);
+        parent[a] = b;
+    }
+
+    let mut root_to_component = vec![None; craft.nodes.len()];
+    let mut components: Vec<Component> = vec![];
+    for node in 0..craft.nodes.len() {
+        let root = find(&mut parent, node);
+        let idx = *root_to_component[root].get_or_insert_with(|| {
+            components.push(Component {
+                nodes: vec![],
+                rods: vec![],
+                dof: 0,
+                clusters: vec![],
+            });
+            components.len() - 1
+        });
+        components[idx].nodes.push(node);
+    }
+    for &i in rods {
+        let root = find(&mut parent, craft.rods[i].node_a);
+        if let Some(idx) = root_to_component[root] {
+            components[idx].rods.push(i);
+        }
+    }
+    components
+}
+
+/// 2D pebble game with two pebbles per node, an edge is independent
+/// when four pebbles can be gathered onto its end points.
+struct PebbleGame {
+    pebbles: Vec<u8>,
+    /// Directed edges, each one covered by a pebble from its tail node
+    out: Vec<Vec<usize>>,
+}
+impl PebbleGame {
+    const PEBBLES: u8 = 2;
+    /// Degrees of freedom of a rigid body in 2D (two translations and a rotation)
+    const TRIVIAL_DOF: usize = 3;
+    /// Nodes closer than this are never braced, the rod would have no length
+    const MIN_BRACE_LENGTH: f32 = 1.0;
+    /// Nearest nodes each node is tried against when looking for braces
+    const BRACE_NEIGHBOURS: usize = 8;
+    /// Largest component every pair of nodes is tried for
+    const FULL_SEARCH_NODES: usize = 200;
+
+    fn new(node_count: usize) -> Self {
+        Self {
+            pebbles: vec![Self::PEBBLES; node_count],
+            out: vec![vec![]; node_count],
+        }
+    }
+
+    /// Tries to insert an edge, returns `false` when it is redundant
+    fn insert(&mut self, a: usize, b: usize) -> bool {
+        if !self.gather(a, b, Self::PEBBLES, Self::PEBBLES) {
+            return false;
+        }
+        self.pebbles[a] -= 1;
+        self.out[a].push(b);
+        true
+    }
+
+    /// Whether adding an edge would remove a degree of freedom, leaves the game unchanged
+    fn is_independent(&mut self, a: usize, b: usize) -> bool {
+        self.gather(a, b, Self::PEBBLES, Self::PEBBLES)
+    }
+
+    /// Moves free pebbles onto `a` and `b` until they hold the requested amounts
+    fn gather(&mut self, a: usize, b: usize, need_a: u8, need_b: u8) -> bool {
+        while self.pebbles[a] < need_a && self.find_pebble(a, &[b]) {}
+        while self.pebbles[b] < need_b && self.find_pebble(b, &[a]) {}
+        self.pebbles[a] >= need_a && self.pebbles[b] >= need_b
+    }
+
+    /// Searches along directed edges for a free pebble and moves it back to `start`,
+    /// pebbles on `blocked` nodes are never taken
+    fn find_pebble(&mut self, start: usize, blocked: &[usize]) -> bool {
+        let mut came_from = vec![None; self.pebbles.len()];
+        let mut visited = vec![false; self.pebbles.len()];
+        let mut stack = vec![start];
+        visited[start] = true;
+
+        while let Some(node) = stack.pop() {
+            for i in 0..self.out[node].len() {
+                let next = self.out[node][i];
+                if visited[next] {
+                    continue;
+                }
+                visited[next] = true;
+                came_from[next] = Some(node);
+
+                if self.pebbles[next] > 0 && !blocked.contains(&next) {
+                    self.reverse_path(&came_from, next);
+                    self.pebbles[next] -= 1;
+                    self.pebbles[start] += 1;
+                    return true;
+                }
+                stack.push(next);
+            }
+        }
+        false
+    }
+
+    /// Flips every edge on the path ending at `end`, so the pebbles covering them shift one step
+    fn reverse_path(&mut self, came_from: &[Option<usize>], end: usize) {
+        let mut node = end;
+        while let Some(prev) = came_from[node] {
+            let i = self.out[prev].iter().position(|&n| n == node).unwrap();
+            self.out[prev].swap_remove(i);
+            self.out[node].push(prev);
+            node = prev;
+        }
+    }
+
+    /// Internal degrees of freedom of a connected group of nodes
+    fn dof(&self, nodes: &[usize]) -> usize {
+        let free: usize = nodes.iter().map(|&n| self.pebbles[n] as usize).sum();
+        let trivial = (nodes.len() * Self::PEBBLES as usize).min(Self::TRIVIAL_DOF);
+        free.saturating_sub(trivial)
+    }
+
+    /// Splits a component's rods into rigid clusters
+    fn rigid_clusters(&mut self, craft: &Craft, rods: &[usize]) -> Vec<Vec<usize>> {
+        let mut assigned = vec![false; rods.len()];
+        let mut clusters = vec![];
+
+        for first in 0..rods.len() {
+            if assigned[first] {
+                continue;
+            }
+            let rod = &craft.rods[rods[first]];
+            let (a, b) = (rod.node_a, rod.node_b);
+            // Three pebbles on a rod pins down its rigid body motion
+            self.gather(a, b, Self::PEBBLES, 1);
+
+            let mut rigid = vec![None; craft.nodes.len()];
+            rigid[a] = Some(true);
+            rigid[b] = Some(true);
+
+            let mut cluster = vec![];
+            for i in first..rods.len() {
+                if assigned[i] {
+                    continue;
+                }
+                let rod = &craft.rods[rods[i]];
+                if self.is_pinned(rod.node_a, &[a, b], &mut rigid)
+                    && self.is_pinned(rod.node_b, &[a, b], &mut rigid)
+                {
+                    assigned[i] = true;
+                    cluster.push(rods[i]);
+                }
+            }
+            clusters.push(cluster);
+        }
+        clusters
+    }
+
+    /// A node is rigidly attached to a pinned rod if no free pebble can be moved to it
+    fn is_pinned(&mut self, node: usize, pinned: &[usize], cache: &mut [Option<bool>]) -> bool {
+        if let Some(rigid) = cache[node] {
+            return rigid;
+        }
+        let rigid = self.pebbles[node] == 0 && !self.find_pebble(node, pinned);
+        cache[node] = Some(rigid);
+        rigid
+    }
+
+    /// Greedily picks the shortest independent rods that make a component rigid
+    fn brace(&mut self, craft: &Craft, component: &Component) -> Vec<(usize, usize)> {
+        let mut braces = vec![];
+        let mut dof = component.dof;
+
+        let near = brace_candidates(craft, &component.nodes, Self::BRACE_NEIGHBOURS);
+        self.insert_braces(near, &mut braces, &mut dof);
+        if dof > 0 && component.nodes.len() <= Self::FULL_SEARCH_NODES {
+            let all = brace_candidates(craft, &component.nodes, component.nodes.len());
+            self.insert_braces(all, &mut braces, &mut dof);
+        }
+        braces
+    }
+    /// Inserts independent candidates in order until `dof` reaches zero
+    fn insert_braces(
+        &mut self,
+        candidates: Vec<(usize, usize)>,
+        braces: &mut Vec<(usize, usize)>,
+        dof: &mut usize,
+    ) {
+        for (a, b) in candidates {
+            if *dof == 0 {
+                break;
+            }
+            if self.is_independent(a, b) {
+                self.insert(a, b);
+                braces.push((a, b));
+                *dof -= 1;
+            }
+        }
+    }
+}
+
+/// Pairs of each node with up to `neighbours` of its nearest nodes, shortest first.
+/// Nodes on top of each other are skipped
+fn brace_candidates(craft: &Craft, nodes: &[usize], neighbours: usize) -> Vec<(usize, usize)> {
+    let min_length = PebbleGame::MIN_BRACE_LENGTH * PebbleGame::MIN_BRACE_LENGTH;
+    let mut candidates = vec![];
+    for &a in nodes {
+        let mut near: Vec<(usize, f32)> = nodes
+            .iter()
+            .map(|&b| (b, craft.nodes[a].pos.distance_squared(craft.nodes[b].pos)))
+            .filter(|&(b, length)| b != a && length >= min_length)
+            .collect();
+        if near.len() > neighbours {
+            near.select_nth_unstable_by(neighbours, |x, y| x.1.total_cmp(&y.1));
+            near.truncate(neighbours);
+        }
+        candidates.extend(
+            near.into_iter()
+                .map(|(b, length)| (a.min(b), a.max(b), length)),
+        );
+    }
+    candidates.sort_by(|x, y| x.2.total_cmp(&y.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+    candidates.dedup_by_key(|c| (c.0, c.1));
+    candidates.into_iter().map(|(a, b, _)| (a, b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft::editor::CraftManager;
+    use macroquad::prelude::{Vec2, vec2};
+
+    /// Builds a craft from node positions and solid rods between them
+    fn craft(points: &[Vec2], rods: &[(usize, usize)]) -> Craft {
+        let mut manager = CraftManager::new(Craft::new());
+        for &pos in points {
+            manager.add_node(pos);
+        }
+        for &(a, b) in rods {
+            manager.add_rod(a, b, RodType::SOLID);
+        }
+        manager.c
+    }
+
+    fn square() -> Vec<Vec2> {
+        vec![
+            vec2(0.0, 0.0),
+            vec2(100.0, 0.0),
+            vec2(100.0, 100.0),
+            vec2(0.0, 100.0),
+        ]
+    }
+
+    #[test]
+    fn triangle_is_rigid() {
+        let points = [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(50.0, 80.0)];
+        let report = RigidityReport::analyse(&craft(&points, &[(0, 1), (1, 2), (2, 0)]));
+        assert!(report.is_rigid());
+        assert!(report.suggested_braces.is_empty());
+        assert_eq!(report.components[0].clusters.len(), 1);
+    }
+
+    #[test]
+    fn square_is_braced_once() {
+        let report = RigidityReport::analyse(&craft(&square(), &[(0, 1), (1, 2), (2, 3), (3, 0)]));
+        assert_eq!(report.total_dof(), 1);
+        assert!(report.is_mechanism_rod(0));
+        assert_eq!(report.suggested_braces.len(), 1);
+
+        let mut braced = vec![(0, 1), (1, 2), (2, 3), (3, 0)];
+        braced.extend(report.suggested_braces);
+        assert!(RigidityReport::analyse(&craft(&square(), &braced)).is_rigid());
+    }
+
+    #[test]
+    fn redundant_rods_add_no_rigidity() {
+        let rods = [(0, 1), (1, 2), (2, 3), (3, 0), (0, 2), (0, 2)];
+        assert!(RigidityReport::analyse(&craft(&square(), &rods)).is_rigid());
+    }
+
+    #[test]
+    fn ropes_are_not_bars() {
+        let mut craft = craft(&square(), &[(0, 1), (1, 2), (2, 3), (3, 0)]);
+        craft.rods[0].rod_type = RodType::ROPE;
+        assert_eq!(RigidityReport::analyse(&craft).total_dof(), 2);
+    }
+
+    #[test]
+    fn coincident_nodes_are_not_braced() {
+        // Node 2 sits on node 1 and is only joined to node 0
+        let points = [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 0.0)];
+        let report = RigidityReport::analyse(&craft(&points, &[(0, 1), (0, 2)]));
+        assert_eq!(report.total_dof(), 1);
+        assert!(report.suggested_braces.is_empty());
+    }
+
+    #[test]
+    fn separate_components_are_counted_apart() {
+        let mut points = square();
+        points.extend(square()[..3].iter().map(|p| *p + vec2(500.0, 0.0)));
+        let rods = [(0, 1), (1, 2), (2, 3), (3, 0), (4, 5), (5, 6), (6, 4)];
+        let report = RigidityReport::analyse(&craft(&points, &rods));
+        assert_eq!(report.components.len(), 2);
+        assert_eq!(report.total_dof(), 1);
+        assert!(!report.is_mechanism_node(4));
+    }
+}
@@ -0,0 +1,188 @@
+//! Vehicle Toolkit
+//!
+//! This module defines a uniform grid used to speed up spatial queries on a craft.
+//!
+//! Each element is stored under every cell its bounding box overlaps, so a query only has
+//! to look at the elements sharing cells with the queried area instead of the whole craft.
+//! The grid stores plain indices, it is up to the owner to keep them in sync with the craft.
+//! Boxes with a non-finite corner, such as those of a simulation which blew up, are ignored,
+//! and elements too large to store cell by cell are returned by every query instead.
+use std::collections::HashMap;
+
+use macroquad::prelude::{Vec2, vec2};
+
+#[derive(Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    /// Elements covering more than `MAX_CELLS` cells
+    oversized: Vec<usize>,
+}
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CELL_SIZE)
+    }
+}
+impl SpatialGrid {
+    const DEFAULT_CELL_SIZE: f32 = 32.0;
+    /// Most cells an element is stored under, or a query visits one by one
+    const MAX_CELLS: i64 = 4096;
+
+    /// Creates an empty grid with square cells of the given size
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            oversized: vec![],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.oversized.clear();
+    }
+
+    fn cell(&self, pos: Vec2) -> (i32, i32) {
+        (
+            (pos.x / self.cell_size).floor() as i32,
+            (pos.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// First and last cell overlapping the box, `None` if it has a non-finite corner
+    fn cell_range(&self, min: Vec2, max: Vec2) -> Option<((i32, i32), (i32, i32))> {
+        if !min.is_finite() || !max.is_finite() {
+            return None;
+        }
+        Some((self.cell(min.min(max)), self.cell(min.max(max))))
+    }
+    fn cell_count(((x0, y0), (x1, y1)): ((i32, i32), (i32, i32))) -> i64 {
+        (x1 as i64 - x0 as i64 + 1).saturating_mul(y1 as i64 - y0 as i64 + 1)
+    }
+
+    /* Adding elements */
+    pub fn insert(&mut self, id: usize, min: Vec2, max: Vec2) {
+        let Some(range) = self.cell_range(min, max) else {
+            return;
+        };
+        if Self::cell_count(range) > Self::MAX_CELLS {
+            self.oversized.push(id);
+            return;
+        }
+        let ((x0, y0), (x1, y1)) = range;
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.cells.entry((x, y)).or_default().push(id);
+            }
+        }
+    }
+    pub fn insert_point(&mut self, id: usize, pos: Vec2) {
+        self.insert(id, pos, pos);
+    }
+    pub fn insert_segment(&mut self, id: usize, a: Vec2, b: Vec2) {
+        self.insert(id, a.min(b), a.max(b));
+    }
+
+    /* Querying elements */
+    /// Returns the sorted ids of every element whose cells overlap the box
+    pub fn query(&self, min: Vec2, max: Vec2) -> Vec<usize> {
+        let Some(range) = self.cell_range(min, max) else {
+            return vec![];
+        };
+        let mut ids = self.oversized.clone();
+        let ((x0, y0), (x1, y1)) = range;
+        if Self::cell_count(range) > Self::MAX_CELLS {
+            // Cheaper to look through the cells in use than every cell in the box
+            for (&(x, y), cell) in &self.cells {
+                if (x0..=x1).contains(&x) && (y0..=y1).contains(&y) {
+                    ids.extend_from_slice(cell);
+                }
+            }
+        } else {
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    if let Some(cell) = self.cells.get(&(x, y)) {
+                        ids.extend_from_slice(cell);
+                    }
+                }
+            }
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+    /// Returns the sorted ids of every element whose cells overlap the square around `pos`
+    pub fn query_radius(&self, pos: Vec2, radius: f32) -> Vec<usize> {
+        self.query(pos - vec2(radius, radius), pos + vec2(radius, radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_found_in_their_cell_only() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert_point(0, vec2(5.0, 5.0));
+        grid.insert_point(1, vec2(25.0, 5.0));
+        assert_eq!(grid.query(vec2(0.0, 0.0), vec2(9.0, 9.0)), vec![0]);
+        assert_eq!(grid.query_radius(vec2(25.0, 5.0), 1.0), vec![1]);
+        assert_eq!(grid.query(vec2(0.0, 0.0), vec2(29.0, 9.0)), vec![0, 1]);
+    }
+
+    #[test]
+    fn negative_coordinates_use_their_own_cells() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert_point(0, vec2(-1.0, -1.0));
+        grid.insert_point(1, vec2(1.0, 1.0));
+        assert_eq!(grid.query_radius(vec2(-5.0, -5.0), 1.0), vec![0]);
+        assert_eq!(grid.query_radius(vec2(5.0, 5.0), 1.0), vec![1]);
+    }
+
+    #[test]
+    fn segments_spanning_many_cells_are_returned_once() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert_segment(3, vec2(45.0, 5.0), vec2(5.0, 5.0));
+        assert_eq!(grid.query_radius(vec2(25.0, 5.0), 1.0), vec![3]);
+        assert_eq!(grid.query(vec2(0.0, 0.0), vec2(50.0, 10.0)), vec![3]);
+        assert!(grid.query_radius(vec2(25.0, 25.0), 1.0).is_empty());
+    }
+
+    #[test]
+    fn reversed_boxes_are_normalised() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(0, vec2(15.0, 15.0), vec2(5.0, 5.0));
+        assert_eq!(grid.query(vec2(19.0, 19.0), vec2(0.0, 0.0)), vec![0]);
+    }
+
+    #[test]
+    fn huge_boxes_dont_visit_every_cell() {
+        let mut grid = SpatialGrid::new(1.0);
+        grid.insert_point(0, vec2(5.0, 5.0));
+        grid.insert(1, vec2(-1e30, -1e30), vec2(1e30, 1e30));
+        assert_eq!(
+            grid.query(Vec2::splat(-f32::MAX), Vec2::splat(f32::MAX)),
+            vec![0, 1]
+        );
+        assert_eq!(grid.query_radius(vec2(-500.0, 0.0), 1.0), vec![1]);
+    }
+
+    #[test]
+    fn non_finite_boxes_are_ignored() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert_point(0, Vec2::NAN);
+        grid.insert(1, vec2(0.0, 0.0), vec2(f32::INFINITY, 5.0));
+        grid.insert_point(2, Vec2::ZERO);
+        assert!(grid.query(Vec2::NAN, Vec2::ZERO).is_empty());
+        assert_eq!(grid.query_radius(Vec2::ZERO, 1.0), vec![2]);
+    }
+
+    #[test]
+    fn clear_removes_everything() {
+        let mut grid = SpatialGrid::default();
+        grid.insert_point(0, Vec2::ZERO);
+        grid.clear();
+        assert!(grid.query_radius(Vec2::ZERO, 100.0).is_empty());
+    }
+}
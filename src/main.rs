@@ -7,6 +7,7 @@
 
 /* Common modules */
 pub mod craft;
+pub mod physics;
 pub mod settings;
 
 mod scenes;
@@ -14,84 +15,269 @@ mod scenes;
 pub mod ui;
 
 mod style;
-use style::build_application_style;
 
-use craft::Craft;
 use macroquad::{
-    input::{KeyCode, is_key_pressed},
+    input::{is_quit_requested, prevent_quit},
     prelude::next_frame,
-    ui::root_ui,
 };
 
-use settings::KeyBinds;
+use scenes::Scene;
+use settings::{
+    Action, InputContext, KeyBinds, SETTINGS_PATH, Settings, gamepad, joystick::Joysticks,
+};
+use ui::modal::{Answer, Dialog, Purpose};
 
 /// These are messages that are passed from the scene to the application
 /// To execute some sort of action that it has no ability too at its level
 pub enum AppMessage {
     None,
+    /// Starts shutting down, asking first if there are unsaved changes
     Quit,
-    OpenMenu,
-    OpenEditor(Option<Craft>),
-    OpenSimulation(Craft),
+    /// Puts a scene on top of the current one, which is paused until it is popped
+    PushScene(Box<dyn Scene>),
+    /// Closes the current scene, resuming the one underneath
+    PopScene,
+    /// Swaps the current scene for another, keeping the ones underneath
+    ReplaceScene(Box<dyn Scene>),
+    /// Replaces the key bindings and saves them to the settings file
+    SetKeyBinds(KeyBinds),
+    /// Opens a dialog over the scene, its answer is given to the scene's `on_dialog`
+    ShowDialog(Dialog),
 }
 
 pub struct Application {
-    mode: Box<dyn scenes::Scene>,
-    key_binds: KeyBinds,
-    show_quit_popup: bool,
+    /// Open scenes, the last one is updated and is never empty until shutting down
+    scenes: Vec<Box<dyn Scene>>,
+    settings: Settings,
+    /// Open dialogs, only the last one takes input and the scene gets none while any are open
+    dialogs: Vec<Dialog>,
+    joysticks: Joysticks,
+    running: bool,
 }
 impl Application {
     pub async fn init() -> Self {
-        let skin = build_application_style().await;
-        root_ui().push_skin(&skin);
+        // Closing the window asks first, like the quit binding
+        prevent_quit();
+        let (settings, error) = Settings::load_or_default();
+        let theme_error = style::apply_theme(settings.theme.as_deref()).await;
 
+        let mut menu = Box::new(scenes::menu::Menu::new());
+        menu.on_enter();
+        let dialogs = error
+            .map(|message| Dialog::error("Error", &message))
+            .into_iter()
+            .chain(theme_error.map(|message| Dialog::error("Theme", &message)))
+            .collect();
         Self {
-            mode: Box::new(scenes::menu::Menu::new()),
-            key_binds: KeyBinds::default(),
-            show_quit_popup: false,
+            scenes: vec![menu],
+            settings,
+            dialogs,
+            joysticks: Joysticks::new(),
+            running: true,
         }
     }
     pub async fn run(&mut self) {
-        loop {
-            // Update with message handling
-            if !self.show_quit_popup {
-                match self.mode.update(&self.key_binds) {
-                    AppMessage::None => (),
-                    AppMessage::Quit => break,
-                    AppMessage::OpenMenu => self.mode = Box::new(scenes::menu::Menu::new()),
-                    AppMessage::OpenEditor(craft) => {
-                        if let Some(craft) = craft {
-                            self.mode = Box::new(scenes::editor::Editor::edit_craft(craft))
-                        } else {
-                            self.mode = Box::new(scenes::editor::Editor::new())
-                        }
-                    }
-                    AppMessage::OpenSimulation(craft) => {
-                        self.mode = Box::new(scenes::simulation::Simulation::new(craft))
-                    }
+        // Errors from closing the scenes are still shown before the window closes
+        while self.running || !self.dialogs.is_empty() {
+            gamepad::update(self.joysticks.poll());
+            if is_quit_requested() {
+                if self.running {
+                    self.request_quit();
+                } else {
+                    self.dialogs.clear();
                 }
             }
 
-            if is_key_pressed(KeyCode::Escape) {
-                self.show_quit_popup = !self.show_quit_popup;
-            }
+            let depth = self.dialogs.len().saturating_sub(1);
+            if let Some(dialog) = self.dialogs.last_mut() {
+                self.settings.key_binds.set_context(InputContext::Menu);
+                if let Some(answer) = dialog.show(depth, &self.settings.key_binds) {
+                    let dialog = self.dialogs.pop().expect("dialog was just shown");
+                    self.answer(dialog.purpose, answer);
+                }
+            } else {
+                // Update with message handling
+                let scene = self.scenes.last_mut().expect("scene stack is never empty");
+                self.settings.key_binds.set_context(scene.context());
+                // Global bindings don't fire while the scene reads keys itself
+                let bindings = !scene.captures_keys();
+                let quit = bindings && self.settings.key_binds.is_key_pressed(Action::Quit);
+                let message = if quit {
+                    scene.on_quit()
+                } else {
+                    scene.update(&self.settings.key_binds)
+                };
+                self.handle(message);
 
-            if self.show_quit_popup {
-                ui::popups::exit_popup(
-                    || {
-                        self.show_quit_popup = false;
-                    },
-                    || {
-                        std::process::exit(0);
-                    },
-                );
+                if bindings && self.settings.key_binds.is_key_pressed(Action::CycleTheme) {
+                    self.cycle_theme().await;
+                }
             }
 
-            // Drawing to screen
-            self.mode.draw();
+            // Drawing to screen, from the top scene which isn't an overlay upwards
+            let base = self
+                .scenes
+                .iter()
+                .rposition(|scene| !scene.is_overlay())
+                .unwrap_or(0);
+            for scene in &self.scenes[base..] {
+                scene.draw();
+            }
             next_frame().await;
         }
     }
+
+    fn scene(&mut self) -> &mut dyn Scene {
+        self.scenes
+            .last_mut()
+            .expect("scene stack is never empty")
+            .as_mut()
+    }
+
+    fn handle(&mut self, message: AppMessage) {
+        match message {
+            AppMessage::None => (),
+            AppMessage::Quit => self.request_quit(),
+            AppMessage::PushScene(scene) => self.push_scene(scene),
+            AppMessage::PopScene => self.pop_scene(),
+            AppMessage::ReplaceScene(scene) => self.replace_scene(scene),
+            AppMessage::SetKeyBinds(key_binds) => {
+                self.settings.key_binds = key_binds;
+                self.save_settings();
+            }
+            AppMessage::ShowDialog(dialog) => self.dialogs.push(dialog),
+        }
+    }
+
+    fn save_settings(&mut self) {
+        if let Err(err) = self.settings.save(SETTINGS_PATH.into()) {
+            let message = format!("Couldn't save settings: {err}");
+            self.dialogs.push(Dialog::error("Error", &message));
+        }
+    }
+
+    /* Themes */
+    /// Switches to the next theme in the themes folder and remembers it in the settings
+    async fn cycle_theme(&mut self) {
+        let names = style::theme_names();
+        if names.is_empty() {
+            return;
+        }
+        let next = self
+            .settings
+            .theme
+            .as_ref()
+            .and_then(|current| names.iter().position(|name| name == current))
+            .map_or(0, |i| (i + 1) % names.len());
+        self.settings.theme = Some(names[next].clone());
+        if let Some(message) = style::apply_theme(self.settings.theme.as_deref()).await {
+            self.dialogs.push(Dialog::error("Theme", &message));
+        }
+        self.save_settings();
+    }
+
+    /* Scene stack */
+    fn push_scene(&mut self, mut scene: Box<dyn Scene>) {
+        self.scene().on_pause();
+        scene.on_enter();
+        self.scenes.push(scene);
+    }
+    /// Popping the last scene goes back to the menu
+    fn pop_scene(&mut self) {
+        if self.scenes.len() == 1 {
+            self.replace_scene(Box::new(scenes::menu::Menu::new()));
+            return;
+        }
+        let mut popped = self.scenes.pop().expect("scene stack is never empty");
+        let message = popped.on_exit();
+        self.scene().on_resume(popped.as_ref());
+        self.handle(message);
+    }
+    fn replace_scene(&mut self, mut scene: Box<dyn Scene>) {
+        let message = self.scene().on_exit();
+        scene.on_enter();
+        *self.scenes.last_mut().expect("scene stack is never empty") = scene;
+        self.handle(message);
+    }
+
+    /* Dialogs */
+    /// Routes a dialog's answer to the application or the scene that asked
+    fn answer(&mut self, purpose: Purpose, answer: Answer) {
+        match purpose {
+            Purpose::Quit => self.answer_quit(answer),
+            Purpose::Notice => (),
+            // The scene which asked has already closed
+            _ if self.scenes.is_empty() => (),
+            _ => {
+                let message = self.scene().on_dialog(purpose, answer);
+                self.handle(message);
+            }
+        }
+    }
+
+    /* Shutting down */
+    /// Asks before quitting, offering to save first if any scene has unsaved changes
+    fn request_quit(&mut self) {
+        if self
+            .dialogs
+            .iter()
+            .any(|dialog| dialog.purpose == Purpose::Quit)
+        {
+            return;
+        }
+        let dialog = if self.has_unsaved_changes() {
+            Dialog::confirm(
+                Purpose::Quit,
+                "Quit",
+                "There are unsaved changes.",
+                "Save and quit",
+            )
+            .or_deny("Discard")
+        } else {
+            Dialog::confirm(
+                Purpose::Quit,
+                "Quit",
+                "Are you sure you want to quit?",
+                "Quit",
+            )
+        };
+        self.dialogs.push(dialog);
+    }
+    fn answer_quit(&mut self, answer: Answer) {
+        match answer {
+            Answer::Confirmed if self.has_unsaved_changes() => match self.save_all() {
+                Ok(()) => self.shut_down(),
+                Err(err) => {
+                    let message = format!("Couldn't save: {err}");
+                    self.dialogs.push(Dialog::error("Error", &message));
+                }
+            },
+            Answer::Confirmed | Answer::Denied => self.shut_down(),
+            Answer::Cancelled | Answer::Text(_) => (),
+        }
+    }
+    /// Closes every scene, replacing any open dialogs with the ones they leave behind
+    fn shut_down(&mut self) {
+        self.dialogs.clear();
+        while let Some(mut scene) = self.scenes.pop() {
+            if let AppMessage::ShowDialog(dialog) = scene.on_exit() {
+                self.dialogs.push(dialog);
+            }
+        }
+        self.running = false;
+    }
+    fn has_unsaved_changes(&self) -> bool {
+        self.scenes.iter().any(|scene| scene.has_unsaved_changes())
+    }
+    /// Saves every scene with unsaved changes, stopping at the first that fails
+    fn save_all(&mut self) -> std::io::Result<()> {
+        for scene in &mut self.scenes {
+            if scene.has_unsaved_changes() {
+                scene.save()?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[macroquad::main("VehicleToolkit")]
@@ -99,3 +285,45 @@ async fn main() {
     let mut app = Application::init().await;
     app.run().await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scene leaving a dialog behind when it closes
+    struct Closing;
+    impl Scene for Closing {
+        fn update(&mut self, _key_binds: &KeyBinds) -> AppMessage {
+            AppMessage::None
+        }
+        fn draw(&self) {}
+        fn context(&self) -> InputContext {
+            InputContext::Editor
+        }
+        fn on_exit(&mut self) -> AppMessage {
+            AppMessage::ShowDialog(Dialog::error("Error", "Closed"))
+        }
+    }
+
+    #[test]
+    fn quitting_with_a_prompt_open_keeps_only_the_exit_dialogs() {
+        let mut app = Application {
+            scenes: vec![Box::new(Closing)],
+            settings: Settings::default(),
+            dialogs: vec![Dialog::prompt(Purpose::SaveCraftAs, "Save", "Name", "")],
+            joysticks: Joysticks::new(),
+            running: true,
+        };
+        app.request_quit();
+        let dialog = app.dialogs.pop().unwrap();
+        assert_eq!(dialog.purpose, Purpose::Quit);
+        app.answer(dialog.purpose, Answer::Denied);
+
+        assert!(!app.running);
+        assert!(app.scenes.is_empty());
+        assert_eq!(app.dialogs.len(), 1);
+        assert_eq!(app.dialogs[0].purpose, Purpose::Notice);
+        // Answering a scene's dialog late does nothing rather than panicking
+        app.answer(Purpose::SaveCraftAs, Answer::Text("Late".to_string()));
+    }
+}
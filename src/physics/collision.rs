@@ -0,0 +1,265 @@
+//! Vehicle Toolkit
+//!
+//! Collision between the parts of the crafts in a world.
+//!
+//! Nodes are treated as small circles and rods as segments. Each step a broad-phase grid is
+//! built from every craft, and only nearby node-node and node-rod pairs are tested, whether
+//! they belong to the same craft or not.
+//!
+//! Pairs are skipped when:
+//!     - A node is one of the ends of the rod, or two nodes share a rod or a hitch
+//!     - A node and a rod are adjacent, the node shares a rod with one of the rod's ends
+//!     - Both parts are in the same craft and have the same non-zero collision group
+use std::collections::HashSet;
+
+use macroquad::prelude::Vec2;
+
+use super::world::{Hitch, NodeRef};
+use crate::craft::{Craft, geometry::point_segment_distance_squared, spatial::SpatialGrid};
+
+/// Radius of a node's collision circle
+pub const NODE_RADIUS: f32 = 6.0;
+
+/// Whether two parts with the given collision groups may collide
+pub fn groups_collide(a: u8, b: u8) -> bool {
+    a == 0 || a != b
+}
+
+#[derive(Default, Clone)]
+pub struct Collision {
+    node_grid: SpatialGrid,
+    rod_grid: SpatialGrid,
+    /// Index of the first node and rod of each craft in the grids
+    node_offsets: Vec<usize>,
+    rod_offsets: Vec<usize>,
+    /// Node pairs joined by a rod or hitch, stored smallest first
+    connected: HashSet<(NodeRef, NodeRef)>,
+}
+impl Collision {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recomputes which nodes are joined, needed whenever crafts or hitches are added or removed
+    pub fn rebuild_connections(&mut self, crafts: &[Craft], hitches: &[Hitch]) {
+        self.connected.clear();
+        for (body, craft) in crafts.iter().enumerate() {
+            for rod in &craft.rods {
+                self.connect(
+                    NodeRef::new(body, rod.node_a),
+                    NodeRef::new(body, rod.node_b),
+                );
+            }
+        }
+        for hitch in hitches {
+            self.connect(hitch.a, hitch.b);
+        }
+    }
+    fn connect(&mut self, a: NodeRef, b: NodeRef) {
+        self.connected.insert((a.min(b), a.max(b)));
+    }
+    fn is_connected(&self, a: NodeRef, b: NodeRef) -> bool {
+        self.connected.contains(&(a.min(b), a.max(b)))
+    }
+
+    /// Pushes apart every overlapping pair of parts
+    pub fn solve(&mut self, crafts: &mut [Craft]) {
+        self.build_grids(crafts);
+
+        for body in 0..crafts.len() {
+            for node in 0..crafts[body].nodes.len() {
+                let node = NodeRef::new(body, node);
+                self.collide_nodes(crafts, node);
+                self.collide_rods(crafts, node);
+            }
+        }
+    }
+
+    fn build_grids(&mut self, crafts: &[Craft]) {
+        self.node_grid.clear();
+        self.rod_grid.clear();
+        self.node_offsets.clear();
+        self.rod_offsets.clear();
+
+        let (mut node_offset, mut rod_offset) = (0, 0);
+        for craft in crafts {
+            self.node_offsets.push(node_offset);
+            self.rod_offsets.push(rod_offset);
+            for (i, node) in craft.nodes.iter().enumerate() {
+                self.node_grid.insert_point(node_offset + i, node.pos);
+            }
+            for (i, rod) in craft.rods.iter().enumerate() {
+                let (a, b) = (craft.nodes[rod.node_a].pos, craft.nodes[rod.node_b].pos);
+                self.rod_grid.insert_segment(rod_offset + i, a, b);
+            }
+            node_offset += craft.nodes.len();
+            rod_offset += craft.rods.len();
+        }
+    }
+
+    /// Splits a grid id back into the craft it belongs to and its index in that craft
+    fn split_id(offsets: &[usize], id: usize) -> (usize, usize) {
+        let body = offsets.partition_point(|&offset| offset <= id) - 1;
+        (body, id - offsets[body])
+    }
+
+    fn collide_nodes(&self, crafts: &mut [Craft], a: NodeRef) {
+        let pos = crafts[a.body].nodes[a.node].pos;
+        for id in self.node_grid.query_radius(pos, NODE_RADIUS * 2.0) {
+            let (body, node) = Self::split_id(&self.node_offsets, id);
+            let b = NodeRef::new(body, node);
+            if b <= a || self.is_connected(a, b) {
+                continue;
+            }
+            let (group_a, group_b) = (
+                crafts[a.body].nodes[a.node].collision_group,
+                crafts[b.body].nodes[b.node].collision_group,
+            );
+            if a.body == b.body && !groups_collide(group_a, group_b) {
+                continue;
+            }
+
+            let delta = crafts[b.body].nodes[b.node].pos - crafts[a.body].nodes[a.node].pos;
+            let dist = delta.length();
+            if dist >= NODE_RADIUS * 2.0 || dist == 0.0 {
+                continue;
+            }
+            let correction = delta / dist * (NODE_RADIUS * 2.0 - dist) * 0.5;
+            crafts[a.body].nodes[a.node].pos -= correction;
+            crafts[b.body].nodes[b.node].pos += correction;
+        }
+    }
+
+    fn collide_rods(&self, crafts: &mut [Craft], n: NodeRef) {
+        let pos = crafts[n.body].nodes[n.node].pos;
+        for id in self.rod_grid.query_radius(pos, NODE_RADIUS) {
+            let (body, r) = Self::split_id(&self.rod_offsets, id);
+            let rod = &crafts[body].rods[r];
+            let (a, b) = (
+                NodeRef::new(body, rod.node_a),
+                NodeRef::new(body, rod.node_b),
+            );
+            if a == n || b == n || self.is_connected(n, a) || self.is_connected(n, b) {
+                continue;
+            }
+            let group = crafts[n.body].nodes[n.node].collision_group;
+            if n.body == body && !groups_collide(group, rod.collision_group) {
+                continue;
+            }
+
+            let p = crafts[n.body].nodes[n.node].pos;
+            let (pa, pb) = (
+                crafts[body].nodes[a.node].pos,
+                crafts[body].nodes[b.node].pos,
+            );
+            let dist_sq = point_segment_distance_squared(p, pa, pb);
+            if dist_sq >= NODE_RADIUS * NODE_RADIUS || dist_sq == 0.0 {
+                continue;
+            }
+
+            // Closest point on the rod, used to share the push between its two ends
+            let ab = pb - pa;
+            let t = if ab.length_squared() == 0.0 {
+                0.0
+            } else {
+                ((p - pa).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+            };
+            let delta: Vec2 = p - (pa + ab * t);
+            let dist = dist_sq.sqrt();
+            let correction = delta / dist * (NODE_RADIUS - dist) * 0.5;
+
+            crafts[n.body].nodes[n.node].pos += correction;
+            crafts[body].nodes[a.node].pos -= correction * (1.0 - t);
+            crafts[body].nodes[b.node].pos -= correction * t;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft::{components::RodType, editor::CraftManager};
+    use macroquad::prelude::vec2;
+
+    /// A craft of loose nodes
+    fn nodes(positions: &[Vec2]) -> Craft {
+        let mut manager = CraftManager::new(Craft::new());
+        for &pos in positions {
+            manager.add_node(pos);
+        }
+        manager.c
+    }
+    fn close_nodes() -> Craft {
+        nodes(&[vec2(0.0, 0.0), vec2(4.0, 0.0)])
+    }
+
+    #[test]
+    fn only_matching_non_zero_groups_pass_through() {
+        assert!(groups_collide(0, 0));
+        assert!(groups_collide(0, 3));
+        assert!(groups_collide(3, 0));
+        assert!(groups_collide(1, 2));
+        assert!(!groups_collide(3, 3));
+    }
+
+    #[test]
+    fn overlapping_nodes_are_pushed_apart() {
+        let mut crafts = [close_nodes()];
+        Collision::new().solve(&mut crafts);
+        let dist = crafts[0].nodes[0].pos.distance(crafts[0].nodes[1].pos);
+        assert!((dist - NODE_RADIUS * 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn nodes_of_different_crafts_collide() {
+        let mut crafts = [nodes(&[vec2(0.0, 0.0)]), nodes(&[vec2(4.0, 0.0)])];
+        let mut collision = Collision::new();
+        collision.rebuild_connections(&crafts, &[]);
+        collision.solve(&mut crafts);
+        let dist = crafts[0].nodes[0].pos.distance(crafts[1].nodes[0].pos);
+        assert!((dist - NODE_RADIUS * 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn nodes_are_pushed_out_of_rods() {
+        let mut manager = CraftManager::new(Craft::new());
+        let a = manager.add_node(vec2(-50.0, 0.0));
+        let b = manager.add_node(vec2(50.0, 0.0));
+        manager.add_rod(a, b, RodType::SOLID);
+        let node = manager.add_node(vec2(0.0, 2.0));
+        let mut crafts = [manager.c];
+
+        let mut collision = Collision::new();
+        collision.rebuild_connections(&crafts, &[]);
+        collision.solve(&mut crafts);
+        let nodes = &crafts[0].nodes;
+        let dist = point_segment_distance_squared(nodes[node].pos, nodes[a].pos, nodes[b].pos);
+        assert!(dist.sqrt() > 2.0);
+        // The rod is pushed the other way, shared evenly between its ends
+        assert!(nodes[node].pos.y > 2.0);
+        assert_eq!(nodes[a].pos.y, nodes[b].pos.y);
+        assert!(nodes[a].pos.y < 0.0);
+    }
+
+    #[test]
+    fn parts_in_the_same_group_pass_through() {
+        let mut crafts = [close_nodes()];
+        for node in &mut crafts[0].nodes {
+            node.collision_group = 1;
+        }
+        Collision::new().solve(&mut crafts);
+        assert_eq!(crafts[0].nodes[0].pos, vec2(0.0, 0.0));
+        assert_eq!(crafts[0].nodes[1].pos, vec2(4.0, 0.0));
+    }
+
+    #[test]
+    fn nodes_joined_by_a_rod_dont_collide() {
+        let mut manager = CraftManager::new(close_nodes());
+        manager.add_rod(0, 1, RodType::SOLID);
+        let mut crafts = [manager.c];
+        let mut collision = Collision::new();
+        collision.rebuild_connections(&crafts, &[]);
+        collision.solve(&mut crafts);
+        assert_eq!(crafts[0].nodes[1].pos, vec2(4.0, 0.0));
+    }
+}
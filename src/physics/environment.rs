@@ -0,0 +1,218 @@
+//! Vehicle Toolkit
+//!
+//! Forces the surroundings put on a craft, besides gravity.
+//!
+//! Every node feels air drag against the wind, and extra drag while under water.
+//! Buoyancy acts on panels, triangles of rods, in proportion to how much of their area is
+//! below the water's surface. The push is shared between the panel's three nodes, so a
+//! craft only floats if it has enough closed panels, like a hull.
+//!
+//! How strong the air drag is, the wind and the water a craft is tested in come from the
+//! physics settings, so they can be tuned without rebuilding.
+use std::collections::BTreeSet;
+
+use macroquad::prelude::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::craft::{
+    Craft,
+    components::{RodType, Vec2Def},
+    geometry::{clip_polygon_to_box, polygon_area},
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Rect")]
+struct RectDef {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+/// Wind blowing across the world, in gusts that travel along with it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Wind {
+    /// Average wind velocity in pixels per second
+    #[serde(with = "Vec2Def")]
+    pub velocity: Vec2,
+    /// How much gusts change the wind speed, as a fraction of the average
+    pub gust: f32,
+}
+impl Wind {
+    /// Distance between gusts in pixels
+    const GUST_SPACING: f32 = 600.0;
+
+    pub fn new(velocity: Vec2, gust: f32) -> Self {
+        Self { velocity, gust }
+    }
+
+    /// Wind velocity at a point and time
+    pub fn at(&self, pos: Vec2, time: f32) -> Vec2 {
+        let speed = self.velocity.length();
+        if speed == 0.0 {
+            return Vec2::ZERO;
+        }
+        let travelled = pos.dot(self.velocity) / speed - speed * time;
+        let phase = travelled / Self::GUST_SPACING * std::f32::consts::TAU;
+        self.velocity * (1.0 + self.gust * phase.sin())
+    }
+}
+
+/// A box of water, its surface is the top edge
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Water {
+    #[serde(with = "RectDef")]
+    pub area: Rect,
+    /// Upward push per unit of submerged panel area, relative to the pull of gravity on a node
+    pub density: f32,
+    /// Fraction of a node's velocity lost per second while under water
+    pub drag: f32,
+}
+impl Water {
+    pub fn new(area: Rect) -> Self {
+        Self {
+            area,
+            density: 0.004,
+            drag: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Environment {
+    pub wind: Wind,
+    pub water: Vec<Water>,
+}
+impl Environment {
+    /// Acceleration of each node of the craft caused by its surroundings,
+    /// `air_drag` is the fraction of a node's velocity relative to the wind lost per second
+    pub fn forces(
+        &self,
+        craft: &Craft,
+        panels: &[[usize; 3]],
+        gravity: Vec2,
+        air_drag: f32,
+        time: f32,
+        dt: f32,
+    ) -> Vec<Vec2> {
+        let mut forces: Vec<Vec2> = craft
+            .nodes
+            .iter()
+            .map(|node| {
+                let velocity = (node.pos - node.prev_pos) / dt;
+                let mut force = (self.wind.at(node.pos, time) - velocity) * air_drag;
+                for water in self.water.iter().filter(|w| w.area.contains(node.pos)) {
+                    force -= velocity * water.drag;
+                }
+                force
+            })
+            .collect();
+
+        let lift = -gravity;
+        for panel in panels {
+            let points = panel.map(|i| craft.nodes[i].pos);
+            for water in &self.water {
+                let (min, max) = (water.area.point(), water.area.point() + water.area.size());
+                let submerged = clip_polygon_to_box(&points, min, max);
+                if submerged.len() < 3 {
+                    continue;
+                }
+                let push = lift * water.density * polygon_area(&submerged) / 3.0;
+                for &i in panel {
+                    forces[i] += push;
+                }
+            }
+        }
+        forces
+    }
+}
+
+/// Every triangle of nodes joined by three rods, ropes are too floppy to make a panel
+pub fn find_panels(craft: &Craft) -> Vec<[usize; 3]> {
+    let mut neighbours = vec![BTreeSet::new(); craft.nodes.len()];
+    for rod in craft
+        .rods
+        .iter()
+        .filter(|rod| rod.rod_type != RodType::ROPE)
+    {
+        neighbours[rod.node_a].insert(rod.node_b);
+        neighbours[rod.node_b].insert(rod.node_a);
+    }
+    let mut panels = vec![];
+    for a in 0..craft.nodes.len() {
+        for &b in neighbours[a].iter().filter(|&&b| b > a) {
+            for &c in neighbours[b].iter().filter(|&&c| c > b) {
+                if neighbours[a].contains(&c) {
+                    panels.push([a, b, c]);
+                }
+            }
+        }
+    }
+    panels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft::editor::CraftManager;
+    use macroquad::prelude::vec2;
+
+    /// Triangle with its point up at the origin and its base along y = 2
+    fn triangle(closing_rod: RodType) -> Craft {
+        let mut manager = CraftManager::new(Craft::new());
+        let a = manager.add_node(vec2(0.0, 0.0));
+        let b = manager.add_node(vec2(1.0, 2.0));
+        let c = manager.add_node(vec2(-1.0, 2.0));
+        manager.add_rod(a, b, RodType::SOLID);
+        manager.add_rod(b, c, RodType::SOLID);
+        manager.add_rod(c, a, closing_rod);
+        manager.c
+    }
+
+    /// Total push of still water with its surface at `surface` on a triangle
+    fn lift(surface: f32) -> Vec2 {
+        let craft = triangle(RodType::SOLID);
+        let mut water = Water::new(Rect::new(-10.0, surface, 20.0, 20.0));
+        water.density = 1.0;
+        let environment = Environment {
+            wind: Wind::default(),
+            water: vec![water],
+        };
+        let panels = find_panels(&craft);
+        let forces = environment.forces(&craft, &panels, vec2(0.0, 1.0), 0.0, 0.0, 1.0);
+        forces.iter().sum()
+    }
+
+    #[test]
+    fn still_air_pushes_nothing() {
+        assert_eq!(Wind::default().at(vec2(40.0, 10.0), 3.0), Vec2::ZERO);
+        let craft = triangle(RodType::SOLID);
+        let forces = Environment::default().forces(&craft, &[], Vec2::ZERO, 0.5, 1.0, 1.0);
+        assert!(forces.iter().all(|&force| force == Vec2::ZERO));
+    }
+
+    #[test]
+    fn gusts_stay_around_the_average() {
+        let wind = Wind::new(vec2(100.0, 0.0), 0.5);
+        for x in [0.0, 150.0, 300.0, 450.0] {
+            let speed = wind.at(vec2(x, 0.0), 0.0).x;
+            assert!((50.0..=150.0).contains(&speed));
+        }
+    }
+
+    #[test]
+    fn lift_follows_the_submerged_area() {
+        let full = lift(-1.0);
+        assert!((full - vec2(0.0, -2.0)).length() < 1e-4);
+        // The part above y = √2 is a similar triangle with half the area
+        let half = lift(2.0f32.sqrt());
+        assert!((half - full * 0.5).length() < 1e-4);
+        assert_eq!(lift(5.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn only_rigid_triangles_are_panels() {
+        assert_eq!(find_panels(&triangle(RodType::SOLID)), vec![[0, 1, 2]]);
+        assert!(find_panels(&triangle(RodType::ROPE)).is_empty());
+    }
+}
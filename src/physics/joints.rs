@@ -0,0 +1,214 @@
+//! Vehicle Toolkit
+//!
+//! Solvers for the hinges of a craft.
+//!
+//! A hinge turns by rotating the nodes of each of its two sub-structures about the pivot.
+//! The sub-structures are found once when a craft is spawned, by walking the rods out from each
+//! arm node without passing through the pivot. Each side is turned in proportion to the size
+//! of the other, so a small arm swings around a large body rather than the other way round.
+//! If the two sides are joined by rods elsewhere, the hinge can't turn without tearing the
+//! craft apart, so it is locked and left alone.
+use std::collections::VecDeque;
+
+use macroquad::prelude::Vec2;
+
+use crate::craft::{Craft, components::Hinge};
+
+/// Number of control channels available to motors and other driven parts
+pub const CHANNELS: usize = 3;
+
+/// Nodes turned by a hinge, neither side contains the pivot
+#[derive(Clone)]
+pub struct HingeSides {
+    a: Vec<usize>,
+    b: Vec<usize>,
+    locked: bool,
+}
+impl HingeSides {
+    /// Finds the sides of every hinge in a craft
+    pub fn find_all(craft: &Craft) -> Vec<HingeSides> {
+        let neighbours = neighbours(craft);
+        craft
+            .hinges
+            .iter()
+            .map(|hinge| {
+                let a = flood(&neighbours, hinge.arm_a, hinge.pivot);
+                if a.contains(&hinge.arm_b) {
+                    return HingeSides {
+                        a: vec![],
+                        b: vec![],
+                        locked: true,
+                    };
+                }
+                HingeSides {
+                    a,
+                    b: flood(&neighbours, hinge.arm_b, hinge.pivot),
+                    locked: false,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the hinge's sides are joined elsewhere, so it doesn't turn
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Turns side b by `angle` relative to side a
+    fn turn(&self, craft: &mut Craft, pivot: Vec2, angle: f32) {
+        let total = (self.a.len() + self.b.len()) as f32;
+        if total == 0.0 {
+            return;
+        }
+        let turn_a = -angle * self.b.len() as f32 / total;
+        let turn_b = angle * self.a.len() as f32 / total;
+        rotate_nodes(craft, &self.a, pivot, turn_a);
+        rotate_nodes(craft, &self.b, pivot, turn_b);
+    }
+}
+
+/// Whether the arms of a hinge are joined by rods without passing through its pivot
+pub fn sides_overlap(craft: &Craft, hinge: &Hinge) -> bool {
+    flood(&neighbours(craft), hinge.arm_a, hinge.pivot).contains(&hinge.arm_b)
+}
+
+/// Nodes sharing a rod with each node
+fn neighbours(craft: &Craft) -> Vec<Vec<usize>> {
+    let mut neighbours = vec![vec![]; craft.nodes.len()];
+    for rod in &craft.rods {
+        neighbours[rod.node_a].push(rod.node_b);
+        neighbours[rod.node_b].push(rod.node_a);
+    }
+    neighbours
+}
+
+/// Every node reachable from `start` without passing through `blocked`
+fn flood(neighbours: &[Vec<usize>], start: usize, blocked: usize) -> Vec<usize> {
+    let mut seen = vec![false; neighbours.len()];
+    seen[blocked] = true;
+    seen[start] = true;
+    let mut queue = VecDeque::from([start]);
+    let mut nodes = vec![];
+    while let Some(node) = queue.pop_front() {
+        nodes.push(node);
+        for &next in &neighbours[node] {
+            if !seen[next] {
+                seen[next] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    nodes
+}
+
+fn rotate_nodes(craft: &mut Craft, nodes: &[usize], pivot: Vec2, angle: f32) {
+    let rotation = Vec2::from_angle(angle);
+    for &i in nodes {
+        let pos = &mut craft.nodes[i].pos;
+        *pos = pivot + rotation.rotate(*pos - pivot);
+    }
+}
+
+/// Signed angle turned from `u` to `v`
+fn signed_angle(u: Vec2, v: Vec2) -> f32 {
+    u.perp_dot(v).atan2(u.dot(v))
+}
+
+/// Current angle from arm a to arm b, using either node positions or previous positions
+fn hinge_angle(craft: &Craft, hinge: &Hinge, previous: bool) -> f32 {
+    let pos = |i: usize| {
+        if previous {
+            craft.nodes[i].prev_pos
+        } else {
+            craft.nodes[i].pos
+        }
+    };
+    let pivot = pos(hinge.pivot);
+    signed_angle(pos(hinge.arm_a) - pivot, pos(hinge.arm_b) - pivot)
+}
+
+/// Speeds motors up or down towards the speed asked for by their control channel,
+/// run once per step
+pub fn solve_motors(craft: &mut Craft, sides: &[HingeSides], controls: &[f32; CHANNELS], dt: f32) {
+    for (i, sides) in sides.iter().enumerate() {
+        let hinge = craft.hinges[i];
+        let Some(motor) = hinge.motor else {
+            continue;
+        };
+        let input = controls.get(motor.channel).copied().unwrap_or(0.0);
+        let target_speed = input.clamp(-1.0, 1.0) * motor.speed;
+
+        let turned =
+            wrap_angle(hinge_angle(craft, &hinge, false) - hinge_angle(craft, &hinge, true));
+        let speed = turned / dt;
+
+        let max_change = motor.strength * dt;
+        let change = (target_speed - speed).clamp(-max_change, max_change);
+        let pivot = craft.nodes[hinge.pivot].pos;
+        sides.turn(craft, pivot, change * dt);
+    }
+}
+
+/// Turns hinges back within their limits, run every constraint iteration
+pub fn solve_limits(craft: &mut Craft, sides: &[HingeSides]) {
+    for (i, sides) in sides.iter().enumerate() {
+        let hinge = craft.hinges[i];
+        let Some((min, max)) = hinge.limits else {
+            continue;
+        };
+        let angle = hinge_angle(craft, &hinge, false);
+        let correction = angle.clamp(min, max) - angle;
+        if correction != 0.0 {
+            let pivot = craft.nodes[hinge.pivot].pos;
+            sides.turn(craft, pivot, correction);
+        }
+    }
+}
+
+/// Wraps an angle into `-PI..=PI`
+fn wrap_angle(angle: f32) -> f32 {
+    signed_angle(Vec2::X, Vec2::from_angle(angle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft::{components::RodType, editor::CraftManager};
+    use macroquad::prelude::vec2;
+
+    /// Two arms hinged on a pivot, optionally joined at their ends by another rod
+    fn hinged(joined: bool) -> Craft {
+        let mut manager = CraftManager::new(Craft::new());
+        let pivot = manager.add_node(vec2(0.0, 0.0));
+        let arm_a = manager.add_node(vec2(-10.0, 0.0));
+        let arm_b = manager.add_node(vec2(10.0, 0.0));
+        manager.add_rod(pivot, arm_a, RodType::SOLID);
+        manager.add_rod(pivot, arm_b, RodType::SOLID);
+        if joined {
+            manager.add_rod(arm_a, arm_b, RodType::SOLID);
+        }
+        manager.add_hinge(arm_a, pivot, arm_b, None);
+        manager.c
+    }
+
+    #[test]
+    fn separate_arms_turn() {
+        let craft = hinged(false);
+        assert!(!sides_overlap(&craft, &craft.hinges[0]));
+        let sides = HingeSides::find_all(&craft);
+        assert!(!sides[0].is_locked());
+        assert_eq!(
+            (sides[0].a.as_slice(), sides[0].b.as_slice()),
+            (&[1][..], &[2][..])
+        );
+    }
+
+    #[test]
+    fn joined_arms_lock_the_hinge() {
+        let craft = hinged(true);
+        assert!(sides_overlap(&craft, &craft.hinges[0]));
+        let sides = HingeSides::find_all(&craft);
+        assert!(sides[0].is_locked());
+        assert!(sides[0].a.is_empty() && sides[0].b.is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+//! Vehicle Toolkit
+//!
+//! This module holds the pieces of the physics engine used by the simulation scene.
+//!
+//! Crafts are simulated with Verlet integration, velocities are implied by the difference
+//! between a node's current and previous position, so every solver here works by moving
+//! node positions directly.
+pub mod collision;
+pub mod environment;
+pub mod joints;
+pub mod ropes;
+pub mod settings;
+pub mod world;
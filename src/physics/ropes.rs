@@ -0,0 +1,227 @@
+//! Vehicle Toolkit
+//!
+//! Segmented ropes.
+//!
+//! A rope rod with `segments` set is split into a chain of shorter ropes when its craft is
+//! spawned, so the rope can sag, wrap around and collide with the terrain like a real one.
+//! The original rod keeps its index and becomes the first link of the chain, the extra nodes
+//! and links are added to the end of the craft so no existing index changes.
+//!
+//! Winches reel a rope in or out by changing the length of every one of its links.
+use macroquad::prelude::Vec2;
+
+use super::joints::CHANNELS;
+use crate::craft::{
+    Craft,
+    components::{Node, Rod, RodType},
+};
+
+/// Links making up a segmented rope, in order from the rope's first node
+#[derive(Clone)]
+pub struct RopeChain {
+    /// Index of the rope rod in the craft as it was built
+    pub rod: usize,
+    pub links: Vec<usize>,
+}
+
+/// Splits every segmented rope in the craft into a chain of links
+pub fn expand_ropes(craft: &mut Craft) -> Vec<RopeChain> {
+    let mut chains = vec![];
+    for i in 0..craft.rods.len() {
+        let rod = craft.rods[i].clone();
+        if rod.rod_type != RodType::ROPE || rod.segments < 2 {
+            continue;
+        }
+
+        let (start, end) = (craft.nodes[rod.node_a].clone(), craft.nodes[rod.node_b].pos);
+        let link_length = rod.length / rod.segments as f32;
+        let mut links = vec![i];
+        let mut prev = rod.node_a;
+        for s in 1..rod.segments {
+            let pos = start.pos.lerp(end, s as f32 / rod.segments as f32);
+            craft.nodes.push(Node {
+                pos,
+                prev_pos: pos,
+                collision_group: rod.collision_group,
+            });
+            let node = craft.nodes.len() - 1;
+
+            // The original rod becomes the first link
+            if s == 1 {
+                craft.rods[i].node_b = node;
+                craft.rods[i].length = link_length;
+            } else {
+                craft.rods.push(Rod {
+                    node_a: prev,
+                    node_b: node,
+                    length: link_length,
+                    winch: None,
+                    ..rod.clone()
+                });
+                links.push(craft.rods.len() - 1);
+            }
+            prev = node;
+        }
+        craft.rods.push(Rod {
+            node_a: prev,
+            node_b: rod.node_b,
+            length: link_length,
+            winch: None,
+            ..rod
+        });
+        links.push(craft.rods.len() - 1);
+
+        chains.push(RopeChain { rod: i, links });
+    }
+    chains
+}
+
+/// Reels winched ropes in or out as asked by their control channel, run once per step.
+/// A winch stalls when reeling in would accelerate the load faster than it is able to.
+pub fn solve_winches(craft: &mut Craft, chains: &[RopeChain], controls: &[f32; CHANNELS], dt: f32) {
+    for i in 0..craft.rods.len() {
+        let Some(winch) = craft.rods[i].winch else {
+            continue;
+        };
+        let single = [i];
+        let links = chains
+            .iter()
+            .find(|chain| chain.rod == i)
+            .map_or(&single[..], |chain| &chain.links[..]);
+
+        let count = links.len() as f32;
+        let length = craft.rods[i].length * count;
+        let span: f32 = links
+            .iter()
+            .map(|&link| {
+                let rod = &craft.rods[link];
+                craft.nodes[rod.node_a]
+                    .pos
+                    .distance(craft.nodes[rod.node_b].pos)
+            })
+            .sum();
+
+        let input = controls.get(winch.channel).copied().unwrap_or(0.0);
+        let reeled = length + input.clamp(-1.0, 1.0) * winch.speed * dt;
+        // Pulling a taut rope in by more than this would accelerate the load past the winch's limit
+        let stall = (span - winch.max_acceleration * dt * dt).min(length);
+        // Not `clamp`, a hand edited craft could have its limits the wrong way round
+        let new_length = reeled
+            .max(stall)
+            .max(winch.min_length)
+            .min(winch.max_length);
+
+        for &link in links {
+            craft.rods[link].length = new_length / count;
+        }
+    }
+}
+
+/// Points along a slack rope hanging between `a` and `b`, sagging towards positive `y`.
+/// The sag is a parabola whose arc length roughly matches the rope's length.
+pub fn slack_points(a: Vec2, b: Vec2, length: f32, count: usize) -> Vec<Vec2> {
+    let span = a.distance(b);
+    let sag = if span > 0.0 && length > span {
+        (3.0 * span * (length - span) / 8.0).sqrt()
+    } else {
+        0.0
+    };
+    (0..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            a.lerp(b, t) + Vec2::Y * sag * 4.0 * t * (1.0 - t)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft::{components::Winch, editor::CraftManager};
+    use macroquad::prelude::vec2;
+
+    fn rope(segments: usize) -> Craft {
+        let mut manager = CraftManager::new(Craft::new());
+        let a = manager.add_node(vec2(0.0, 0.0));
+        let b = manager.add_node(vec2(100.0, 0.0));
+        let rod = manager.add_rod(a, b, RodType::ROPE);
+        manager.c.rods[rod].segments = segments;
+        manager.add_rod(a, b, RodType::SOLID);
+        manager.c
+    }
+
+    #[test]
+    fn segmented_ropes_become_chains() {
+        let mut craft = rope(4);
+        let chains = expand_ropes(&mut craft);
+
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].rod, 0);
+        assert_eq!(chains[0].links.len(), 4);
+        assert_eq!(craft.nodes.len(), 5);
+        // Existing indices are kept, the solid rod is still second
+        assert!(craft.rods[1].rod_type == RodType::SOLID);
+
+        // Links run from the first node to the last, evenly spaced
+        let mut node = 0;
+        for &link in &chains[0].links {
+            let rod = &craft.rods[link];
+            assert_eq!(rod.node_a, node);
+            assert!((rod.length - 25.0).abs() < 1e-4);
+            node = rod.node_b;
+        }
+        assert_eq!(node, 1);
+        assert_eq!(craft.nodes[2].pos, vec2(25.0, 0.0));
+    }
+
+    #[test]
+    fn plain_ropes_are_left_alone() {
+        for segments in [0, 1] {
+            let mut craft = rope(segments);
+            assert!(expand_ropes(&mut craft).is_empty());
+            assert_eq!((craft.nodes.len(), craft.rods.len()), (2, 2));
+        }
+    }
+
+    #[test]
+    fn winches_reel_out_past_the_built_length() {
+        let mut craft = rope(0);
+        craft.rods[0].winch = Some(Winch {
+            channel: 0,
+            ..Winch::new(100.0)
+        });
+        let controls = [1.0, 0.0, 0.0];
+        for _ in 0..200 {
+            solve_winches(&mut craft, &[], &controls, 0.1);
+        }
+        assert_eq!(craft.rods[0].length, 200.0);
+    }
+
+    #[test]
+    fn reversed_winch_limits_do_not_panic() {
+        let mut craft = rope(0);
+        craft.rods[0].winch = Some(Winch {
+            channel: 0,
+            min_length: 150.0,
+            max_length: 50.0,
+            ..Winch::new(100.0)
+        });
+        solve_winches(&mut craft, &[], &[1.0, 0.0, 0.0], 0.1);
+        assert_eq!(craft.rods[0].length, 50.0);
+    }
+
+    #[test]
+    fn slack_ropes_sag_in_the_middle() {
+        let points = slack_points(vec2(0.0, 0.0), vec2(100.0, 0.0), 120.0, 4);
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[0], vec2(0.0, 0.0));
+        assert_eq!(points[4], vec2(100.0, 0.0));
+        assert!(points[2].y > points[1].y && points[1].y > 0.0);
+    }
+
+    #[test]
+    fn taut_ropes_are_straight() {
+        let points = slack_points(vec2(0.0, 0.0), vec2(100.0, 0.0), 80.0, 4);
+        assert!(points.iter().all(|p| p.y == 0.0));
+    }
+}
@@ -0,0 +1,74 @@
+//! Vehicle Toolkit
+//!
+//! Tunable parameters of the physics solver.
+//!
+//! The defaults are read from a JSON file when a simulation starts, and a craft can carry
+//! its own settings in its save file so it is always tested in the world it was built for.
+//! Besides the solver, this covers the air, the wind and the pool of water the craft is tested in.
+//! There is no air drag or water by default, levels which want them carry their own settings.
+use std::{fs::File, io, path::PathBuf};
+
+use macroquad::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::environment::{Water, Wind};
+use crate::craft::components::Vec2Def;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct PhysicsSettings {
+    /// Acceleration in pixels per second squared
+    #[serde(with = "Vec2Def")]
+    pub gravity: Vec2,
+    /// Times the constraints are solved per substep, more is stiffer but slower
+    pub iterations: usize,
+    /// Pieces each fixed step is split into
+    pub substeps: usize,
+    /// Fraction of a node's speed kept when bouncing off the floor
+    pub restitution: f32,
+    /// Fraction of a node's sliding speed lost each step it touches the floor
+    pub friction: f32,
+    /// Fraction of every node's velocity lost per second
+    pub damping: f32,
+    /* Environment */
+    /// Fraction of a node's velocity relative to the wind lost per second
+    pub air_drag: f32,
+    /// Wind blowing while it is turned on
+    pub wind: Wind,
+    /// Water the craft can be tested in, if any
+    pub pool: Option<Water>,
+}
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: Vec2::new(0.0, 500.0),
+            iterations: 5,
+            substeps: 1,
+            restitution: 0.3,
+            friction: 0.2,
+            damping: 0.0,
+            air_drag: 0.0,
+            wind: Wind::new(Vec2::new(150.0, 0.0), 0.5),
+            pool: None,
+        }
+    }
+}
+impl PhysicsSettings {
+    /// Loads settings from a JSON file, missing fields keep their defaults
+    pub fn load(file_path: PathBuf) -> io::Result<PhysicsSettings> {
+        let file = File::open(file_path)?;
+        serde_json::from_reader(file).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "JSON physics settings format seems to be invalid",
+            )
+        })
+    }
+
+    /// Saves settings into a JSON file
+    pub fn save(&self, file_path: PathBuf) -> io::Result<()> {
+        let file = File::create(file_path)?;
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|_| io::Error::other("Error saving physics settings"))
+    }
+}
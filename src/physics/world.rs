@@ -0,0 +1,444 @@
+//! Vehicle Toolkit
+//!
+//! The world holds every craft taking part in a simulation.
+//!
+//! Crafts are spawned as independent copies, so the same craft can be placed many times.
+//! A world can be cloned to take a snapshot of the simulation and restore it later.
+//! Crafts collide with each other and with the floor, and can be joined by hitches,
+//! detachable joints which hold a node of one craft at a fixed distance from a node of another.
+//! Besides gravity, crafts are pushed around by the world's environment of wind and water.
+use macroquad::prelude::Vec2;
+
+use super::{
+    collision::Collision,
+    environment::{self, Environment},
+    joints::{self, CHANNELS, HingeSides},
+    ropes::{self, RopeChain},
+    settings::PhysicsSettings,
+};
+use crate::craft::{
+    Craft,
+    components::{Rod, RodType},
+    spatial::SpatialGrid,
+};
+
+/// Identifies a node of a specific craft in the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeRef {
+    pub body: usize,
+    pub node: usize,
+}
+impl NodeRef {
+    pub fn new(body: usize, node: usize) -> Self {
+        Self { body, node }
+    }
+}
+
+/// Detachable joint between nodes of two crafts
+#[derive(Clone, Copy, PartialEq)]
+pub struct Hitch {
+    pub a: NodeRef,
+    pub b: NodeRef,
+    pub length: f32,
+}
+
+/// A node being pulled towards a point, like being dragged by hand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grab {
+    pub node: NodeRef,
+    pub target: Vec2,
+}
+
+/// Where a craft is spawned relative to where it was built
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Placement {
+    pub offset: Vec2,
+    /// Rotation in radians around the craft's centre
+    pub angle: f32,
+}
+impl Placement {
+    pub fn new(offset: Vec2, angle: f32) -> Self {
+        Self { offset, angle }
+    }
+
+    /// Moves a point of a craft centred on `centre` into place
+    pub fn apply(&self, centre: Vec2, pos: Vec2) -> Vec2 {
+        centre + Vec2::from_angle(self.angle).rotate(pos - centre) + self.offset
+    }
+}
+
+#[derive(Clone)]
+pub struct World {
+    pub settings: PhysicsSettings,
+    pub floor: f32,
+    pub environment: Environment,
+    pub crafts: Vec<Craft>,
+    pub hitches: Vec<Hitch>,
+    pub grab: Option<Grab>,
+    /// Inputs for motors and other driven parts, each within `-1.0..=1.0`
+    pub controls: [f32; CHANNELS],
+    /// Nodes turned by each hinge, per craft
+    hinge_sides: Vec<Vec<HingeSides>>,
+    /// Links of each segmented rope, per craft
+    rope_chains: Vec<Vec<RopeChain>>,
+    /// Triangles feeling buoyancy, per craft
+    panels: Vec<Vec<[usize; 3]>>,
+    collision: Collision,
+    time: f32,
+    /// Length of the last substep, zero before the first
+    last_dt: f32,
+}
+impl World {
+    /// Length of a single physics step in seconds
+    pub const FIXED_DT: f32 = 1.0 / 120.0;
+    /// Fraction of the distance to its target a grabbed node is moved each iteration
+    const GRAB_STIFFNESS: f32 = 0.2;
+
+    pub fn new(settings: PhysicsSettings, floor: f32) -> Self {
+        Self {
+            settings,
+            floor,
+            environment: Environment::default(),
+            crafts: vec![],
+            hitches: vec![],
+            grab: None,
+            controls: [0.0; CHANNELS],
+            hinge_sides: vec![],
+            rope_chains: vec![],
+            panels: vec![],
+            collision: Collision::new(),
+            time: 0.0,
+            last_dt: 0.0,
+        }
+    }
+
+    /* Adding and removing crafts */
+    /// Adds a copy of the craft moved into place, returns its index.
+    /// Segmented ropes are split into chains, so the copy may have more nodes and rods.
+    pub fn spawn(&mut self, craft: &Craft, placement: Placement) -> usize {
+        let centre = craft.centre();
+        let mut craft = craft.clone();
+        for node in &mut craft.nodes {
+            node.pos = placement.apply(centre, node.pos);
+            node.prev_pos = placement.apply(centre, node.prev_pos);
+        }
+        self.rope_chains.push(ropes::expand_ropes(&mut craft));
+        self.hinge_sides.push(HingeSides::find_all(&craft));
+        self.panels.push(environment::find_panels(&craft));
+        self.crafts.push(craft);
+        self.collision
+            .rebuild_connections(&self.crafts, &self.hitches);
+        self.crafts.len() - 1
+    }
+
+    /* Hitches */
+    /// Joins two nodes at their current distance, returns the hitch index
+    pub fn hitch(&mut self, a: NodeRef, b: NodeRef) -> usize {
+        let length = self.node(a).distance(self.node(b));
+        self.hitches.push(Hitch { a, b, length });
+        self.collision
+            .rebuild_connections(&self.crafts, &self.hitches);
+        self.hitches.len() - 1
+    }
+    pub fn detach(&mut self, hitch_id: usize) -> Option<Hitch> {
+        if hitch_id >= self.hitches.len() {
+            return None;
+        }
+        let hitch = self.hitches.remove(hitch_id);
+        self.collision
+            .rebuild_connections(&self.crafts, &self.hitches);
+        Some(hitch)
+    }
+    /// Closest pair of nodes from different crafts within `max_dist` of each other
+    pub fn nearest_hitch_pair(&self, max_dist: f32) -> Option<(NodeRef, NodeRef)> {
+        let nodes: Vec<NodeRef> = self
+            .crafts
+            .iter()
+            .enumerate()
+            .flat_map(|(body, craft)| (0..craft.nodes.len()).map(move |i| NodeRef::new(body, i)))
+            .collect();
+        let mut grid = SpatialGrid::new(max_dist.max(1.0));
+        for (id, &node) in nodes.iter().enumerate() {
+            grid.insert_point(id, self.node(node));
+        }
+
+        let mut best: Option<(NodeRef, NodeRef, f32)> = None;
+        for &a in &nodes {
+            let pos = self.node(a);
+            for id in grid.query_radius(pos, max_dist) {
+                let b = nodes[id];
+                if b.body <= a.body {
+                    continue;
+                }
+                let dist = pos.distance(self.node(b));
+                if dist < max_dist && best.is_none_or(|(_, _, d)| dist < d) {
+                    best = Some((a, b, dist));
+                }
+            }
+        }
+        best.map(|(a, b, _)| (a, b))
+    }
+
+    /// Number of hinges which can't turn because their sides are joined elsewhere
+    pub fn locked_hinges(&self) -> usize {
+        self.hinge_sides
+            .iter()
+            .flatten()
+            .filter(|sides| sides.is_locked())
+            .count()
+    }
+
+    pub fn node(&self, node: NodeRef) -> Vec2 {
+        self.crafts[node.body].nodes[node.node].pos
+    }
+    /// Closest node of any craft within `radius` of `pos`
+    pub fn nearest_node(&self, pos: Vec2, radius: f32) -> Option<NodeRef> {
+        self.crafts
+            .iter()
+            .enumerate()
+            .flat_map(|(body, craft)| {
+                craft
+                    .nodes
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, node)| (NodeRef::new(body, i), node.pos.distance_squared(pos)))
+            })
+            .filter(|(_, dist)| *dist < radius * radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(node, _)| node)
+    }
+
+    /* Simulation */
+    /// Length of each substep when stepping by `FIXED_DT`
+    pub fn substep_dt(&self) -> f32 {
+        Self::FIXED_DT / self.settings.substeps.max(1) as f32
+    }
+
+    /// Advances the world by one step, `dt` should normally be `FIXED_DT`
+    pub fn step(&mut self, dt: f32) {
+        let substeps = self.settings.substeps.max(1);
+        for _ in 0..substeps {
+            self.substep(dt / substeps as f32);
+        }
+    }
+
+    fn substep(&mut self, dt: f32) {
+        self.rescale_velocities(dt);
+        self.time += dt;
+        let PhysicsSettings {
+            gravity,
+            iterations,
+            restitution,
+            friction,
+            damping,
+            air_drag,
+            ..
+        } = self.settings;
+
+        let forces: Vec<Vec<Vec2>> = self
+            .crafts
+            .iter()
+            .zip(&self.panels)
+            .map(|(craft, panels)| {
+                self.environment
+                    .forces(craft, panels, gravity, air_drag, self.time, dt)
+            })
+            .collect();
+
+        // Verlet integration
+        for (craft, forces) in self.crafts.iter_mut().zip(forces) {
+            for (node, force) in craft.nodes.iter_mut().zip(forces) {
+                let temp = node.pos;
+                let velocity = (node.pos - node.prev_pos) * (1.0 - damping * dt).max(0.0);
+                node.pos += velocity + (gravity + force) * dt * dt;
+                node.prev_pos = temp;
+
+                // Floor collision
+                if node.pos.y > self.floor {
+                    node.pos.y = self.floor;
+                    let mut velocity = node.pos - node.prev_pos;
+                    velocity.y *= -restitution;
+                    velocity.x *= 1.0 - friction;
+                    node.prev_pos = node.pos - velocity;
+                }
+            }
+        }
+
+        for (craft, sides) in self.crafts.iter_mut().zip(&self.hinge_sides) {
+            joints::solve_motors(craft, sides, &self.controls, dt);
+        }
+        for (craft, chains) in self.crafts.iter_mut().zip(&self.rope_chains) {
+            ropes::solve_winches(craft, chains, &self.controls, dt);
+        }
+
+        // Constraints
+        for _ in 0..iterations {
+            for (craft, sides) in self.crafts.iter_mut().zip(&self.hinge_sides) {
+                Self::solve_rods(craft, self.time);
+                joints::solve_limits(craft, sides);
+            }
+            self.solve_hitches();
+            self.solve_grab();
+            self.collision.solve(&mut self.crafts);
+        }
+    }
+
+    /// Nodes keep their velocity as the distance moved last substep, so when the substep
+    /// length changes that distance is scaled to match, keeping the same speed
+    fn rescale_velocities(&mut self, dt: f32) {
+        if self.last_dt > 0.0 && self.last_dt != dt {
+            let scale = dt / self.last_dt;
+            for node in self.crafts.iter_mut().flat_map(|craft| &mut craft.nodes) {
+                node.prev_pos = node.pos - (node.pos - node.prev_pos) * scale;
+            }
+        }
+        self.last_dt = dt;
+    }
+
+    /// Length a rod is trying to reach at the given simulation time
+    fn target_length(rod: &Rod, time: f32) -> f32 {
+        match rod.rod_type {
+            RodType::SOLID | RodType::ROPE => rod.length,
+            RodType::SPRING => 100.0,
+            // Dynamic length, could be user-controlled or animated
+            // For now, just placeholder behavior
+            RodType::PISTON => 150.0 + 50.0 * time.sin(),
+        }
+    }
+    /// How far a rod is stretched from its target length, as a fraction of that length,
+    /// negative when compressed. Ropes go slack instead of compressing, so never go below zero
+    pub fn strain(&self, craft: &Craft, rod: &Rod) -> f32 {
+        let target = Self::target_length(rod, self.time);
+        if target == 0.0 {
+            return 0.0;
+        }
+        let length = craft.nodes[rod.node_a]
+            .pos
+            .distance(craft.nodes[rod.node_b].pos);
+        let strain = (length - target) / target;
+        match rod.rod_type {
+            RodType::ROPE => strain.max(0.0),
+            _ => strain,
+        }
+    }
+
+    fn solve_rods(craft: &mut Craft, time: f32) {
+        for i in 0..craft.rods.len() {
+            let rod = &craft.rods[i];
+            let (a, b) = (rod.node_a, rod.node_b);
+            let (pa, pb) = (craft.nodes[a].pos, craft.nodes[b].pos);
+            let delta = pb - pa;
+            let dist = delta.length();
+            if dist == 0.0 {
+                continue;
+            }
+            let dir = delta / dist;
+            let target = Self::target_length(rod, time);
+
+            let correction = match rod.rod_type {
+                RodType::SOLID | RodType::PISTON => {
+                    let diff = dist - target;
+                    dir * (diff * 0.5)
+                }
+                RodType::ROPE => {
+                    if dist <= target {
+                        continue;
+                    }
+                    let diff = dist - target;
+                    dir * (diff * 0.5)
+                }
+                RodType::SPRING => {
+                    // Simple spring: pull or push nodes toward rest length
+                    let k = 0.2;
+                    dir * (dist - target) * k
+                }
+            };
+            craft.nodes[a].pos += correction;
+            craft.nodes[b].pos -= correction;
+        }
+    }
+
+    /// Pulls the grabbed node towards its target, its velocity is kept when let go
+    fn solve_grab(&mut self) {
+        let Some(Grab { node, target }) = self.grab else {
+            return;
+        };
+        let pos = &mut self.crafts[node.body].nodes[node.node].pos;
+        *pos += (target - *pos) * Self::GRAB_STIFFNESS;
+    }
+
+    fn solve_hitches(&mut self) {
+        for i in 0..self.hitches.len() {
+            let Hitch { a, b, length } = self.hitches[i];
+            let delta = self.node(b) - self.node(a);
+            let dist = delta.length();
+            if dist == 0.0 {
+                continue;
+            }
+            let correction = delta / dist * (dist - length) * 0.5;
+            self.crafts[a.body].nodes[a.node].pos += correction;
+            self.crafts[b.body].nodes[b.node].pos -= correction;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft::editor::CraftManager;
+    use macroquad::prelude::vec2;
+
+    fn point(pos: Vec2) -> Craft {
+        let mut manager = CraftManager::new(Craft::new());
+        manager.add_node(pos);
+        manager.c
+    }
+
+    #[test]
+    fn hitch_pair_is_the_closest_across_crafts() {
+        let mut world = World::new(PhysicsSettings::default(), 1000.0);
+        let origin = Placement::new(Vec2::ZERO, 0.0);
+        world.spawn(&point(vec2(0.0, 0.0)), origin);
+        world.spawn(&point(vec2(30.0, 0.0)), origin);
+        world.spawn(&point(vec2(5.0, 0.0)), origin);
+
+        let pair = world.nearest_hitch_pair(20.0);
+        assert_eq!(pair, Some((NodeRef::new(0, 0), NodeRef::new(2, 0))));
+        assert_eq!(world.nearest_hitch_pair(4.0), None);
+    }
+
+    #[test]
+    fn changing_substeps_keeps_the_speed() {
+        let settings = PhysicsSettings {
+            gravity: Vec2::ZERO,
+            air_drag: 0.0,
+            pool: None,
+            ..PhysicsSettings::default()
+        };
+        let mut world = World::new(settings, 1000.0);
+        world.spawn(&point(vec2(0.0, 0.0)), Placement::new(Vec2::ZERO, 0.0));
+        world.crafts[0].nodes[0].prev_pos = vec2(-1.0, 0.0);
+        world.step(World::FIXED_DT);
+
+        world.settings.substeps = 4;
+        let start = world.node(NodeRef::new(0, 0));
+        world.step(World::FIXED_DT);
+        let moved = world.node(NodeRef::new(0, 0)) - start;
+        assert!((moved.x - 1.0).abs() < 1e-4, "moved {moved}");
+    }
+
+    #[test]
+    fn slack_ropes_have_no_strain() {
+        let world = World::new(PhysicsSettings::default(), 1000.0);
+        let mut manager = CraftManager::new(Craft::new());
+        let a = manager.add_node(vec2(0.0, 0.0));
+        let b = manager.add_node(vec2(100.0, 0.0));
+        let rope = manager.add_rod(a, b, RodType::ROPE);
+        let solid = manager.add_rod(a, b, RodType::SOLID);
+        manager.c.nodes[b].pos = vec2(50.0, 0.0);
+
+        let craft = &manager.c;
+        assert_eq!(world.strain(craft, &craft.rods[rope]), 0.0);
+        assert!(world.strain(craft, &craft.rods[solid]) < 0.0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Vehicle Toolkit
+//!
+//! Camera shared by the scenes which show a craft, handling pan and zoom from the key binds.
+use std::ops::{Deref, DerefMut};
+
+use macroquad::prelude::{Camera2D, Rect, Vec2, screen_height, screen_width, vec2};
+
+use crate::settings::{Action, KeyBinds};
+
+const MAX_ZOOM: f32 = 20.0;
+const MIN_ZOOM: f32 = 0.1;
+/// World units moved per frame while a pan key is held
+const PAN_SPEED: f32 = 1.0;
+
+pub struct SceneCamera {
+    camera: Camera2D,
+    default_zoom: f32,
+}
+impl SceneCamera {
+    /// Camera whose world `y` axis points up the screen, centred on the origin
+    pub fn y_up() -> Self {
+        Self::new(Camera2D::from_display_rect(Rect {
+            x: -screen_width() / 2.,
+            y: -screen_height() / 2.,
+            w: screen_width(),
+            h: screen_height(),
+        }))
+    }
+    /// Camera matching screen coordinates, world `y` points down and `(0, 0)` is the top left
+    pub fn y_down() -> Self {
+        Self::new(Camera2D {
+            target: vec2(screen_width() / 2., screen_height() / 2.),
+            zoom: vec2(2. / screen_width(), 2. / screen_height()),
+            ..Default::default()
+        })
+    }
+    fn new(camera: Camera2D) -> Self {
+        Self {
+            default_zoom: camera.zoom.x,
+            camera,
+        }
+    }
+
+    /// Pans the camera with the `MoveCam*` actions, returns whether it moved
+    pub fn pan_input(&mut self, key_binds: &KeyBinds) -> bool {
+        // Screen up is the direction of decreasing y unless the camera flips the y axis
+        let up = if self.camera.zoom.y < 0.0 { 1.0 } else { -1.0 };
+        let mut pan = Vec2::ZERO;
+        if key_binds.is_key_down(Action::MoveCamLeft) {
+            pan.x -= 1.0;
+        }
+        if key_binds.is_key_down(Action::MoveCamRight) {
+            pan.x += 1.0;
+        }
+        if key_binds.is_key_down(Action::MoveCamUp) {
+            pan.y += up;
+        }
+        if key_binds.is_key_down(Action::MoveCameDown) {
+            pan.y -= up;
+        }
+        self.camera.target += pan * PAN_SPEED;
+        pan != Vec2::ZERO
+    }
+    /// Zooms the camera with the `Zoom*` actions
+    pub fn zoom_input(&mut self, key_binds: &KeyBinds) {
+        if key_binds.is_key_down(Action::ZoomIn) {
+            self.zoom(1.1);
+        }
+        if key_binds.is_key_down(Action::ZoomOut) {
+            self.zoom(0.9);
+        }
+    }
+
+    /// Scales the camera zoom, keeping it within `MIN_ZOOM..MAX_ZOOM` of the default zoom
+    pub fn zoom(&mut self, factor: f32) {
+        let level = (self.camera.zoom.x * factor / self.default_zoom).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.camera.zoom *= level * self.default_zoom / self.camera.zoom.x;
+    }
+
+    /// Converts a distance in screen pixels into world units
+    pub fn pixels_to_world(&self, pixels: f32) -> f32 {
+        pixels * 2.0 / (screen_width() * self.camera.zoom.x.abs())
+    }
+
+    /// Region of the world currently on screen
+    pub fn visible_rect(&self) -> Rect {
+        let a = self.camera.screen_to_world(Vec2::ZERO);
+        let b = self
+            .camera
+            .screen_to_world(vec2(screen_width(), screen_height()));
+        let (min, max) = (a.min(b), a.max(b));
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+}
+impl Deref for SceneCamera {
+    type Target = Camera2D;
+
+    fn deref(&self) -> &Camera2D {
+        &self.camera
+    }
+}
+impl DerefMut for SceneCamera {
+    fn deref_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+}
@@ -1,24 +1,79 @@
-use super::Scene;
-use crate::craft::editor::CraftManager;
+use std::{
+    f32::consts::FRAC_PI_2,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Scene, camera::SceneCamera, simulation::Simulation};
+use crate::craft::editor::{CraftManager, SelectMode};
+use crate::craft::rigidity::RigidityReport;
 use crate::craft::{Craft, components::*, draw_craft};
+use crate::physics::joints::{CHANNELS, sides_overlap};
 use crate::{
     AppMessage,
-    settings::{Action, KeyBinds},
+    settings::{Action, InputContext, KeyBinds},
+    style::palette,
+    ui::{
+        Panel, Widget,
+        modal::{Answer, Dialog, Purpose},
+    },
 };
 
-use macroquad::prelude::*;
+use macroquad::{
+    prelude::*,
+    ui::{hash, root_ui},
+};
 
 const THRESHOLD: f32 = 20.0;
-const SELECT_COLOR: Color = Color::from_hex(0x1ffdff);
-const MAX_ZOOM: f32 = 20.0;
-const MIN_ZOOM: f32 = 0.1;
+/// Folder crafts are saved into, the menu lists crafts from here
+const CRAFTS_PATH: &str = "data/crafts";
+/// Where unsaved work is autosaved, offered back on the next start if the app didn't close
+const RECOVERY_PATH: &str = "data/recovery.json";
+/// Seconds between autosaves
+const AUTOSAVE_INTERVAL: f32 = 60.0;
+const TITLE_BAR_HEIGHT: f32 = 30.0;
+/// Mouse movement in pixels below which a drag is treated as a click
+const CLICK_DISTANCE: f32 = 4.0;
+/// Kinds of rod that can be placed, cycled through with `CycleRodType`
+const ROD_TOOLS: [(&str, RodType, usize); 5] = [
+    ("Solid", RodType::SOLID, 0),
+    ("Rope", RodType::ROPE, 0),
+    ("Segmented rope", RodType::ROPE, 8),
+    ("Spring", RodType::SPRING, 0),
+    ("Piston", RodType::PISTON, 0),
+];
 
 #[derive(Debug, Clone, PartialEq)]
 enum Selected {
     New(Vec2),
     Node(usize),
     Rod(usize),
-    Selected(usize),
+    Previous(usize),
+}
+
+/// Shape drawn when dragging the mouse to select
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DragTool {
+    Box,
+    Lasso,
+}
+
+/// Autosaved craft, along with the file it was being edited from
+#[derive(Serialize, Deserialize)]
+struct Recovery {
+    path: Option<PathBuf>,
+    craft: Craft,
+}
+
+/// How a click or drag changes the current selection
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SelectOp {
+    Add,
+    Subtract,
+    Toggle,
 }
 
 pub struct Editor {
@@ -26,121 +81,280 @@ pub struct Editor {
     selected_points: Vec<Selected>,
     drag_start: Option<Vec2>,
     drag_current: Option<Vec2>,
-    camera: Camera2D,
+    lasso: Vec<Vec2>,
+    drag_tool: DragTool,
+    select_mode: SelectMode,
+    /// Index into `ROD_TOOLS`
+    rod_tool: usize,
+    camera: SceneCamera,
+    /// Rigidity analysis of the craft, only kept up to date while the overlay is shown
+    rigidity: Option<RigidityReport>,
+    /// File the craft is saved to, `None` until it is first saved
+    path: Option<PathBuf>,
+    /// Revision of the craft written to the recovery file
+    autosaved_revision: u64,
+    /// Seconds since the last autosave
+    autosave_timer: f32,
+    /// Where Save As will write once replacing the craft already there is confirmed
+    overwrite_path: Option<PathBuf>,
+    /// Hinge being edited and its settings panel, while its pivot is the only selected point
+    hinge_panel: Option<(usize, Panel<Hinge>)>,
 }
 
 impl Scene for Editor {
     fn update(&mut self, key_binds: &KeyBinds) -> AppMessage {
         let mouse_world = self.camera.screen_to_world(mouse_position().into());
         if key_binds.is_key_pressed(Action::SwitchScene) {
-            return AppMessage::OpenSimulation(self.manager.c.clone());
+            let simulation = Simulation::new(self.manager.c.clone());
+            return AppMessage::PushScene(Box::new(simulation));
         }
-        // Cam Movement
-        {
-            if key_binds.is_key_down(Action::MoveCamLeft) {
-                self.camera.target.x -= 1.0;
-            }
-            if key_binds.is_key_down(Action::MoveCamRight) {
-                self.camera.target.x += 1.0;
-            }
-            if key_binds.is_key_down(Action::MoveCamUp) {
-                self.camera.target.y += 1.0;
-            }
-            if key_binds.is_key_down(Action::MoveCameDown) {
-                self.camera.target.y -= 1.0;
-            }
-            if key_binds.is_key_down(Action::ZoomIn) {
-                self.camera.zoom *= 1.1;
-            }
-            if key_binds.is_key_down(Action::ZoomOut) {
-                self.camera.zoom *= 0.9;
+        self.autosave_timer += get_frame_time();
+        if self.autosave_timer >= AUTOSAVE_INTERVAL {
+            self.autosave_timer = 0.0;
+            if let Err(err) = self.autosave() {
+                let message = format!("Couldn't autosave: {err}");
+                return AppMessage::ShowDialog(Dialog::error("Error", &message));
             }
         }
 
-        if is_mouse_button_pressed(MouseButton::Left) {
-            let selected = self
-                .selected(mouse_world, THRESHOLD)
-                .unwrap_or((Selected::New(mouse_world), 0.0))
-                .0;
-            self.selected_points.push(selected);
+        // Cam Movement
+        self.camera.pan_input(key_binds);
+        self.camera.zoom_input(key_binds);
+
+        if key_binds.is_key_pressed(Action::ToggleLasso) {
+            self.drag_tool = match self.drag_tool {
+                DragTool::Box => DragTool::Lasso,
+                DragTool::Lasso => DragTool::Box,
+            };
+        }
+        if key_binds.is_key_pressed(Action::ToggleSelectMode) {
+            self.select_mode = match self.select_mode {
+                SelectMode::Contained => SelectMode::Intersecting,
+                SelectMode::Intersecting => SelectMode::Contained,
+            };
         }
 
-        if is_mouse_button_pressed(MouseButton::Left) {
+        if is_mouse_button_pressed(MouseButton::Left)
+            && !root_ui().is_mouse_over(mouse_position().into())
+        {
             self.drag_start = Some(mouse_world);
             self.drag_current = Some(mouse_world);
+            self.lasso = vec![mouse_world];
         }
 
-        if is_mouse_button_down(MouseButton::Left) {
-            if let Some(_) = self.drag_start {
-                self.drag_current = Some(mouse_world);
+        if is_mouse_button_down(MouseButton::Left) && self.drag_start.is_some() {
+            self.drag_current = Some(mouse_world);
+            if self
+                .lasso
+                .last()
+                .is_none_or(|&p| p.distance(mouse_world) > 1.0)
+            {
+                self.lasso.push(mouse_world);
             }
         }
 
         if is_mouse_button_released(MouseButton::Left) {
+            let op = if key_binds.is_key_down(Action::SelectSubtract) {
+                SelectOp::Subtract
+            } else if key_binds.is_key_down(Action::SelectToggle) {
+                SelectOp::Toggle
+            } else {
+                SelectOp::Add
+            };
+
             if let (Some(start), Some(end)) = (self.drag_start, self.drag_current) {
-                self.select_within_box(start, end);
+                let dragged = self
+                    .camera
+                    .world_to_screen(start)
+                    .distance(mouse_position().into());
+                if dragged < CLICK_DISTANCE {
+                    self.select_at(end, op);
+                } else {
+                    match self.drag_tool {
+                        DragTool::Box => self.select_within_box(start, end, op),
+                        DragTool::Lasso => self.select_within_lasso(op),
+                    }
+                }
             }
             self.drag_start = None;
             self.drag_current = None;
+            self.lasso.clear();
+        }
+
+        let mut edited = false;
+
+        // Settings of the hinge pivoting on the selected node
+        let hinge_id = match self.selected_points.as_slice() {
+            [Selected::Node(id)] => self.manager.c.hinges.iter().position(|h| h.pivot == *id),
+            _ => None,
+        };
+        if let Some(id) = hinge_id {
+            let hinge = self.manager.c.hinges[id];
+            if self
+                .hinge_panel
+                .as_ref()
+                .is_none_or(|(shown, _)| *shown != id)
+            {
+                self.hinge_panel = Some((id, hinge_panel(&hinge)));
+            }
+            let (_, panel) = self.hinge_panel.as_mut().expect("panel was just built");
+            let mut edit = hinge;
+            let size = vec2(340.0, 300.0);
+            let pos = vec2(screen_width() - size.x - 20.0, TITLE_BAR_HEIGHT + 20.0);
+            panel.show(hash!("hinge_panel"), pos, size, &mut edit);
+            if edit != hinge {
+                self.manager.c.hinges[id] = edit;
+                self.manager.mark_edited();
+                // Rebuilt so widgets for switched on or off settings appear or go
+                self.hinge_panel = Some((id, hinge_panel(&edit)));
+                edited = true;
+            }
+        } else {
+            self.hinge_panel = None;
+        }
+
+        if key_binds.is_key_pressed(Action::SaveCraft) {
+            return match &self.path {
+                Some(path) => self.save_message(path.clone()),
+                None => self.save_as_prompt(),
+            };
+        }
+        if key_binds.is_key_pressed(Action::SaveCraftAs) {
+            return self.save_as_prompt();
         }
 
         if key_binds.is_key_pressed(Action::NewCraft) {
-            self.manager.c = Craft::new();
+            if self.manager.is_dirty() {
+                return AppMessage::ShowDialog(Dialog::confirm(
+                    Purpose::NewCraft,
+                    "New craft",
+                    "Discard unsaved changes?",
+                    "Discard",
+                ));
+            }
+            self.new_craft();
         }
 
         if key_binds.is_key_pressed(Action::ClearPoints) {
             self.selected_points.clear();
         }
 
-        if key_binds.is_key_pressed(Action::PlaceNodes) && self.selected_points.len() >= 1 {
+        if key_binds.is_key_pressed(Action::PlaceNodes) && !self.selected_points.is_empty() {
             for point in &self.selected_points {
                 let pos = match point {
                     Selected::New(pos) => *pos,
                     Selected::Node(id) => self.manager.c.nodes[*id].pos,
                     Selected::Rod(id) => self.manager.rod_midpoint(*id),
-                    Selected::Selected(_) => continue,
+                    Selected::Previous(_) => continue,
                 };
                 self.manager.add_node(pos);
             }
             self.selected_points.clear();
+            edited = true;
+        }
+
+        if key_binds.is_key_pressed(Action::CycleRodType) {
+            self.rod_tool = (self.rod_tool + 1) % ROD_TOOLS.len();
         }
 
         if key_binds.is_key_pressed(Action::PlaceRods) && self.selected_points.len() >= 2 {
+            let (_, rod_type, segments) = &ROD_TOOLS[self.rod_tool];
             for i in 0..self.selected_points.len() - 1 {
                 let node_a = self.ensure_node(i);
                 let node_b = self.ensure_node(i + 1);
 
-                self.manager.add_rod(node_a, node_b, RodType::default());
+                let rod = self.manager.add_rod(node_a, node_b, rod_type.clone());
+                self.manager.c.rods[rod].segments = *segments;
             }
             self.selected_points.clear();
+            edited = true;
         }
 
-        if key_binds.is_key_pressed(Action::Delete) && !self.selected_points.is_empty() {
-            let mut node_ids = Vec::new();
-            let mut rod_ids = Vec::new();
-
-            for sel in &self.selected_points {
-                match sel {
-                    Selected::Node(id) => node_ids.push(*id),
-                    Selected::Rod(id) => rod_ids.push(*id),
-                    Selected::New(_) | Selected::Selected(_) => {} // Ignore
+        // Hinges are placed from three points, the first arm, the pivot and the second arm
+        let place_motor = key_binds.is_key_pressed(Action::PlaceMotor);
+        if (key_binds.is_key_pressed(Action::PlaceHinge) || place_motor)
+            && self.selected_points.len() == 3
+        {
+            // Checked before any new nodes are made, so a rejected hinge leaves nothing behind
+            if let Some(problem) = self.hinge_problem() {
+                self.selected_points.clear();
+                return AppMessage::ShowDialog(Dialog::error("Can't place hinge", problem));
+            }
+            let arm_a = self.ensure_node(0);
+            let pivot = self.ensure_node(1);
+            let arm_b = self.ensure_node(2);
+            let motor = place_motor.then(Motor::default);
+            self.manager.add_hinge(arm_a, pivot, arm_b, motor);
+            self.selected_points.clear();
+            edited = true;
+        }
+
+        // Winches can only be fitted to ropes
+        if key_binds.is_key_pressed(Action::ToggleWinch) && !self.selected_points.is_empty() {
+            let (_, rod_ids) = self.selected_components();
+            for id in rod_ids {
+                let rod = &mut self.manager.c.rods[id];
+                if rod.rod_type == RodType::ROPE {
+                    rod.winch = match rod.winch {
+                        Some(_) => None,
+                        None => Some(Winch::new(rod.length)),
+                    };
+                    self.manager.mark_edited();
+                    edited = true;
                 }
             }
+        }
+
+        if key_binds.is_key_pressed(Action::Delete) && !self.selected_points.is_empty() {
+            let (node_ids, rod_ids) = self.selected_components();
 
             self.manager.remove_rods(&rod_ids);
             self.manager.remove_nodes(&node_ids);
 
             self.selected_points.clear();
+            edited = true;
+        }
+
+        if key_binds.is_key_pressed(Action::GroupSelection) && !self.selected_points.is_empty() {
+            let (node_ids, rod_ids) = self.selected_components();
+            if self.manager.group_components(&node_ids, &rod_ids).is_none() {
+                return AppMessage::ShowDialog(Dialog::error(
+                    "Error",
+                    "Every collision group is already in use.",
+                ));
+            }
+            edited = true;
+        }
+
+        if key_binds.is_key_pressed(Action::AddBracing)
+            && let Some(report) = &self.rigidity
+        {
+            for &(a, b) in &report.suggested_braces {
+                self.manager.add_rod(a, b, RodType::SOLID);
+            }
+            edited = true;
+        }
+
+        if key_binds.is_key_pressed(Action::ToggleRigidity) {
+            self.rigidity = match self.rigidity {
+                Some(_) => None,
+                None => Some(RigidityReport::analyse(&self.manager.c)),
+            };
+        } else if edited && self.rigidity.is_some() {
+            self.rigidity = Some(RigidityReport::analyse(&self.manager.c));
         }
         AppMessage::None
     }
 
     fn draw(&self) {
-        clear_background(WHITE);
+        let palette = palette();
+        clear_background(palette.background);
 
-        set_camera(&self.camera);
-        //set_camera(&self.camera);
+        set_camera(&*self.camera);
         draw_craft(&self.manager.c);
+        if let Some(report) = &self.rigidity {
+            self.draw_rigidity(report);
+        }
 
         for sel in &self.selected_points {
             match sel {
@@ -149,59 +363,402 @@ impl Scene for Editor {
                     let p1 = self.manager.c.nodes[r.node_a].pos;
                     let p2 = self.manager.c.nodes[r.node_b].pos;
 
-                    draw_line(p1.x, p1.y, p2.x, p2.y, 3.0, SELECT_COLOR);
+                    draw_line(p1.x, p1.y, p2.x, p2.y, 3.0, palette.select);
                 }
                 _ => {
-                    let pos = self.resolve_selected_point(&sel);
-                    draw_circle(pos.x, pos.y, 6.0, SELECT_COLOR);
+                    let pos = self.resolve_selected_point(sel);
+                    draw_circle(pos.x, pos.y, 6.0, palette.select);
                 }
             }
         }
-        if let (Some(start), Some(end)) = (self.drag_start, self.drag_current) {
+        if self.drag_tool == DragTool::Lasso && self.lasso.len() > 1 {
+            for i in 0..self.lasso.len() {
+                let (a, b) = (self.lasso[i], self.lasso[(i + 1) % self.lasso.len()]);
+                draw_line(a.x, a.y, b.x, b.y, 1.0, palette.select);
+            }
+        } else if let (DragTool::Box, Some(start), Some(end)) =
+            (self.drag_tool, self.drag_start, self.drag_current)
+        {
             let top_left = start.min(end);
             let size = (start - end).abs();
-            draw_rectangle_lines(top_left.x, top_left.y, size.x, size.y, 1.0, SELECT_COLOR);
+            draw_rectangle_lines(top_left.x, top_left.y, size.x, size.y, 1.0, palette.select);
             draw_rectangle(
                 top_left.x,
                 top_left.y,
                 size.x,
                 size.y,
-                SELECT_COLOR.with_alpha(0.7),
+                palette.select.with_alpha(0.7),
             );
         }
         set_default_camera();
+
+        if let Some(report) = &self.rigidity {
+            self.draw_rigidity_labels(report);
+        }
+
+        // Title bar, marked with a star while there are unsaved changes
+        draw_rectangle(0.0, 0.0, screen_width(), TITLE_BAR_HEIGHT, palette.panel);
+        let star = if self.manager.is_dirty() { "*" } else { "" };
+        let title = format!("{}{star}", self.title());
+        draw_text(&title, 10.0, TITLE_BAR_HEIGHT - 8.0, 24.0, palette.text);
+
+        let tool = match (self.drag_tool, self.select_mode) {
+            (DragTool::Box, SelectMode::Contained) => "Box select (contained)",
+            (DragTool::Box, SelectMode::Intersecting) => "Box select (intersecting)",
+            (DragTool::Lasso, SelectMode::Contained) => "Lasso select (contained)",
+            (DragTool::Lasso, SelectMode::Intersecting) => "Lasso select (intersecting)",
+        };
+        draw_text(tool, 10.0, screen_height() - 10.0, 24.0, palette.text);
+        draw_text(
+            &format!("Rod: {}", ROD_TOOLS[self.rod_tool].0),
+            10.0,
+            screen_height() - 34.0,
+            24.0,
+            palette.text,
+        );
+    }
+
+    fn context(&self) -> InputContext {
+        InputContext::Editor
+    }
+
+    fn craft(&self) -> Option<&Craft> {
+        Some(&self.manager.c)
+    }
+
+    /// Keeps physics settings saved to the craft while it was being simulated
+    fn on_resume(&mut self, popped: &dyn Scene) {
+        if let Some(craft) = popped.craft()
+            && craft.physics != self.manager.c.physics
+        {
+            self.manager.c.physics = craft.physics;
+            self.manager.mark_edited();
+        }
+    }
+
+    /// Closing the editor on purpose means the recovery file isn't needed
+    fn on_exit(&mut self) -> AppMessage {
+        match Editor::discard_recovery() {
+            Ok(()) => AppMessage::None,
+            Err(err) => {
+                let message = format!("Couldn't remove recovery file: {err}");
+                AppMessage::ShowDialog(Dialog::error("Error", &message))
+            }
+        }
+    }
+
+    fn on_dialog(&mut self, purpose: Purpose, answer: Answer) -> AppMessage {
+        match (purpose, answer) {
+            (Purpose::SaveCraftAs, Answer::Text(name)) => {
+                let name = name.trim();
+                if let Some(problem) = name_problem(name) {
+                    return AppMessage::ShowDialog(Dialog::error("Error", problem));
+                }
+                let path = PathBuf::from(format!("{CRAFTS_PATH}/{name}.json"));
+                if path.exists() && self.path.as_ref() != Some(&path) {
+                    let message = format!("{name} already exists, do you want to replace it?");
+                    self.overwrite_path = Some(path);
+                    return AppMessage::ShowDialog(
+                        Dialog::confirm(
+                            Purpose::OverwriteCraft,
+                            "Replace craft",
+                            &message,
+                            "Replace",
+                        )
+                        .or_deny("Rename"),
+                    );
+                }
+                self.save_message(path)
+            }
+            (Purpose::OverwriteCraft, answer) => match (self.overwrite_path.take(), answer) {
+                (Some(path), Answer::Confirmed) => self.save_message(path),
+                (_, Answer::Denied) => self.save_as_prompt(),
+                _ => AppMessage::None,
+            },
+            (Purpose::NewCraft, Answer::Confirmed) => {
+                self.new_craft();
+                AppMessage::None
+            }
+            _ => AppMessage::None,
+        }
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        self.manager.is_dirty()
+    }
+    /// Saves to the craft's file, or a new untitled file if it hasn't been saved before
+    fn save(&mut self) -> io::Result<()> {
+        let path = self.path.clone().unwrap_or_else(untitled_path);
+        self.save_to(path)
     }
 }
 impl Editor {
     pub fn new() -> Self {
-        Self {
-            manager: CraftManager { c: Craft::new() },
-            selected_points: Vec::new(),
-            drag_start: None,
-            drag_current: None,
-            camera: Camera2D::from_display_rect(Rect {
-                x: -screen_width() / 2.,
-                y: -screen_height() / 2.,
-                w: screen_width(),
-                h: screen_height(),
-            }),
-        }
+        Self::edit_craft(Craft::new())
     }
 
     pub fn edit_craft(craft: Craft) -> Self {
         Self {
-            manager: CraftManager { c: craft },
+            manager: CraftManager::new(craft),
             selected_points: Vec::new(),
             drag_start: None,
             drag_current: None,
-            camera: Camera2D::from_display_rect(Rect {
-                x: -screen_width() / 2.,
-                y: -screen_height() / 2.,
-                w: screen_width(),
-                h: screen_height(),
-            }),
+            lasso: Vec::new(),
+            drag_tool: DragTool::Box,
+            select_mode: SelectMode::Contained,
+            rod_tool: 0,
+            camera: SceneCamera::y_up(),
+            rigidity: None,
+            path: None,
+            autosaved_revision: 0,
+            autosave_timer: 0.0,
+            overwrite_path: None,
+            hinge_panel: None,
+        }
+    }
+
+    /// Edits a craft loaded from `path`, saving goes back to the same file
+    pub fn edit_file(craft: Craft, path: PathBuf) -> Self {
+        let mut editor = Self::edit_craft(craft);
+        editor.path = Some(path);
+        editor
+    }
+}
+
+/// Saving and crash recovery
+impl Editor {
+    /// Name shown in the title bar
+    fn title(&self) -> String {
+        self.path.as_deref().and_then(Path::file_stem).map_or_else(
+            || "Untitled".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    fn save_to(&mut self, path: PathBuf) -> io::Result<()> {
+        self.manager.c.save(path.clone())?;
+        self.path = Some(path);
+        self.manager.mark_saved();
+        Editor::discard_recovery()
+    }
+    /// Saves to `path`, with a dialog explaining what went wrong if it fails
+    fn save_message(&mut self, path: PathBuf) -> AppMessage {
+        match self.save_to(path) {
+            Ok(()) => AppMessage::None,
+            Err(err) => {
+                let message = format!("Couldn't save {}: {err}", self.title());
+                AppMessage::ShowDialog(Dialog::error("Error", &message))
+            }
+        }
+    }
+    fn save_as_prompt(&self) -> AppMessage {
+        let name = self
+            .path
+            .as_ref()
+            .map_or_else(String::new, |_| self.title());
+        AppMessage::ShowDialog(Dialog::prompt(
+            Purpose::SaveCraftAs,
+            "Save craft as",
+            "Name",
+            &name,
+        ))
+    }
+
+    /// Starts over with an empty craft which isn't saved anywhere yet
+    fn new_craft(&mut self) {
+        self.manager.set_craft(Craft::new());
+        self.manager.mark_saved();
+        self.path = None;
+        self.selected_points.clear();
+        if self.rigidity.is_some() {
+            self.rigidity = Some(RigidityReport::analyse(&self.manager.c));
+        }
+    }
+
+    /// Writes the craft to the recovery file if it changed since it was last written
+    fn autosave(&mut self) -> io::Result<()> {
+        let revision = self.manager.revision();
+        if !self.manager.is_dirty() || revision == self.autosaved_revision {
+            return Ok(());
+        }
+        let recovery = Recovery {
+            path: self.path.clone(),
+            craft: self.manager.c.clone(),
+        };
+        let file = File::create(RECOVERY_PATH)?;
+        serde_json::to_writer(file, &recovery).map_err(io::Error::other)?;
+        self.autosaved_revision = revision;
+        Ok(())
+    }
+
+    /// Whether there is autosaved work left over from a session which didn't close properly
+    pub fn has_recovery() -> bool {
+        Path::new(RECOVERY_PATH).exists()
+    }
+    /// Opens the autosaved craft, it counts as unsaved until saved again
+    pub fn recover() -> io::Result<Self> {
+        let file = File::open(RECOVERY_PATH)?;
+        let recovery: Recovery = serde_json::from_reader(file).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Recovery file seems to be invalid",
+            )
+        })?;
+        let mut editor = match recovery.path {
+            Some(path) => Self::edit_file(recovery.craft, path),
+            None => Self::edit_craft(recovery.craft),
+        };
+        editor.manager.mark_edited();
+        editor.autosaved_revision = editor.manager.revision();
+        Ok(editor)
+    }
+    pub fn discard_recovery() -> io::Result<()> {
+        match fs::remove_file(RECOVERY_PATH) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Switches and sliders for a hinge's limits and motor, starting from `hinge`
+fn hinge_panel(hinge: &Hinge) -> Panel<Hinge> {
+    let mut panel = Panel::titled("Hinge").with(Widget::toggle(
+        "Limited",
+        hinge.limits.is_some(),
+        |hinge: &mut Hinge, on| hinge.limits = on.then_some((-FRAC_PI_2, FRAC_PI_2)),
+    ));
+    if let Some((min, max)) = hinge.limits {
+        panel.push(Widget::slider(
+            "Min angle",
+            -180.0..180.0,
+            min.to_degrees(),
+            |hinge: &mut Hinge, value| {
+                if let Some((min, max)) = &mut hinge.limits {
+                    *min = value.to_radians();
+                    *max = max.max(*min);
+                }
+            },
+        ));
+        panel.push(Widget::slider(
+            "Max angle",
+            -180.0..180.0,
+            max.to_degrees(),
+            |hinge: &mut Hinge, value| {
+                if let Some((min, max)) = &mut hinge.limits {
+                    *max = value.to_radians();
+                    *min = min.min(*max);
+                }
+            },
+        ));
+    }
+
+    panel.push(Widget::toggle(
+        "Motor",
+        hinge.motor.is_some(),
+        |hinge: &mut Hinge, on| hinge.motor = on.then(Motor::default),
+    ));
+    if let Some(motor) = hinge.motor {
+        panel.push(Widget::slider(
+            "Channel",
+            1.0..CHANNELS as f32,
+            (motor.channel + 1) as f32,
+            |hinge: &mut Hinge, value| {
+                if let Some(motor) = &mut hinge.motor {
+                    motor.channel = (value.round() as usize).clamp(1, CHANNELS) - 1;
+                }
+            },
+        ));
+        panel.push(Widget::slider(
+            "Speed",
+            0.0..10.0,
+            motor.speed,
+            |hinge: &mut Hinge, value| {
+                if let Some(motor) = &mut hinge.motor {
+                    motor.speed = value;
+                }
+            },
+        ));
+    }
+    panel
+}
+
+/// Why a craft can't be saved under `name`, names must stay inside the crafts folder
+fn name_problem(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("The craft needs a name")
+    } else if name.contains(['/', '\\']) {
+        Some("Craft names can't contain slashes")
+    } else if name.starts_with('.') {
+        Some("Craft names can't start with a dot")
+    } else {
+        None
+    }
+}
+
+/// First `Untitled` craft file name which isn't taken
+fn untitled_path() -> PathBuf {
+    (1..)
+        .map(|i| match i {
+            1 => PathBuf::from(format!("{CRAFTS_PATH}/Untitled.json")),
+            _ => PathBuf::from(format!("{CRAFTS_PATH}/Untitled {i}.json")),
+        })
+        .find(|path| !path.exists())
+        .expect("there is always a free name")
+}
+
+/// Helper functions for drawing the rigidity overlay
+impl Editor {
+    fn draw_rigidity(&self, report: &RigidityReport) {
+        let palette = palette();
+        let craft = &self.manager.c;
+        for (i, rod) in craft.rods.iter().enumerate() {
+            if report.is_mechanism_rod(i) {
+                let (a, b) = (craft.nodes[rod.node_a].pos, craft.nodes[rod.node_b].pos);
+                draw_line(a.x, a.y, b.x, b.y, 3.0, palette.mechanism);
+            }
+        }
+        for (i, node) in craft.nodes.iter().enumerate() {
+            if report.is_mechanism_node(i) {
+                draw_circle(node.pos.x, node.pos.y, 6.0, palette.mechanism);
+            }
+        }
+        for &(a, b) in &report.suggested_braces {
+            let (a, b) = (craft.nodes[a].pos, craft.nodes[b].pos);
+            draw_line(a.x, a.y, b.x, b.y, 2.0, palette.brace.with_alpha(0.6));
         }
     }
+    /// Labels each mechanism with its degrees of freedom, drawn in screen space
+    fn draw_rigidity_labels(&self, report: &RigidityReport) {
+        let palette = palette();
+        for component in report.components.iter().filter(|c| !c.is_rigid()) {
+            let centre = self
+                .camera
+                .world_to_screen(self.manager.midpoint(&component.nodes));
+            draw_text(
+                &format!("{} DOF", component.dof),
+                centre.x,
+                centre.y,
+                24.0,
+                palette.mechanism,
+            );
+        }
+        let status = if report.is_rigid() {
+            "Craft is rigid".to_string()
+        } else {
+            format!(
+                "{} DOF, {} braces suggested",
+                report.total_dof(),
+                report.suggested_braces.len()
+            )
+        };
+        draw_text(
+            &status,
+            10.0,
+            TITLE_BAR_HEIGHT + 24.0,
+            24.0,
+            palette.mechanism,
+        );
+    }
 }
 
 /// Helper functions for selecting parts
@@ -211,7 +768,7 @@ impl Editor {
             Selected::New(pos) => *pos,
             Selected::Node(id) => self.manager.c.nodes[*id].pos,
             Selected::Rod(id) => self.manager.rod_midpoint(*id),
-            Selected::Selected(index) => {
+            Selected::Previous(index) => {
                 if let Some(inner) = self.selected_points.get(*index) {
                     self.resolve_selected_point(inner)
                 } else {
@@ -221,20 +778,12 @@ impl Editor {
         }
     }
     fn selected_node(&self, pos: Vec2, threshold: f32) -> Option<(Selected, f32)> {
-        let i = self.manager.select_nearest_node(pos)?;
-        let dist = self.manager.c.nodes[i].pos.distance_squared(pos);
-        if dist < threshold * threshold {
-            return Some((Selected::Node(i), dist));
-        }
-        None
+        let (i, dist) = self.manager.select_nearest_node(pos, threshold)?;
+        Some((Selected::Node(i), dist))
     }
     fn selected_rod(&self, pos: Vec2, threshold: f32) -> Option<(Selected, f32)> {
-        let i = self.manager.select_nearest_rod(pos)?;
-        let dist = self.manager.rod_midpoint(i).distance_squared(pos);
-        if dist < threshold * threshold {
-            return Some((Selected::Rod(i), dist));
-        }
-        None
+        let (i, dist) = self.manager.select_nearest_rod(pos, threshold)?;
+        Some((Selected::Rod(i), dist))
     }
     fn selected_select(&self, pos: Vec2, threshold: f32) -> Option<(Selected, f32)> {
         self.selected_points
@@ -243,7 +792,7 @@ impl Editor {
             .filter_map(|(i, sel)| {
                 let dist = self.resolve_selected_point(sel).distance_squared(pos);
                 if dist < threshold * threshold {
-                    Some((Selected::Selected(i), dist))
+                    Some((Selected::Previous(i), dist))
                 } else {
                     None
                 }
@@ -266,6 +815,57 @@ impl Editor {
         // Return the closest one
         candidates.into_iter().min_by(|a, b| a.1.total_cmp(&b.1))
     }
+    /// Index of the selected point a `Previous` selection refers back to
+    fn original_point(&self, index: usize) -> usize {
+        match self.selected_points[index] {
+            Selected::Previous(earlier) => self.original_point(earlier),
+            _ => index,
+        }
+    }
+    /// Existing node a selected point is on, `None` if placing there makes a new node
+    fn existing_node(&self, index: usize) -> Option<usize> {
+        match self.selected_points[self.original_point(index)] {
+            Selected::Node(id) => Some(id),
+            _ => None,
+        }
+    }
+    /// Why a hinge can't be placed on the three selected points, if it can't
+    fn hinge_problem(&self) -> Option<&'static str> {
+        let points: Vec<(Option<usize>, usize)> = (0..3)
+            .map(|i| (self.existing_node(i), self.original_point(i)))
+            .collect();
+        let same = |a: (Option<usize>, usize), b: (Option<usize>, usize)| match (a.0, b.0) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.1 == b.1,
+        };
+        if same(points[0], points[1]) || same(points[1], points[2]) || same(points[0], points[2]) {
+            return Some("A hinge needs three different points.");
+        }
+        // New nodes have no rods yet, so only hinges between existing nodes can clash
+        let (Some(arm_a), Some(pivot), Some(arm_b)) = (points[0].0, points[1].0, points[2].0)
+        else {
+            return None;
+        };
+        let craft = &self.manager.c;
+        let duplicate = craft.hinges.iter().any(|hinge| {
+            let arms = (hinge.arm_a, hinge.arm_b);
+            hinge.pivot == pivot && (arms == (arm_a, arm_b) || arms == (arm_b, arm_a))
+        });
+        if duplicate {
+            return Some("There is already a hinge there.");
+        }
+        let hinge = Hinge {
+            pivot,
+            arm_a,
+            arm_b,
+            limits: None,
+            motor: None,
+        };
+        if sides_overlap(craft, &hinge) {
+            return Some("The arms are joined by other rods, so the hinge couldn't turn.");
+        }
+        None
+    }
     /// Converts any selected point into a concrete node (by creating one if needed)
     fn ensure_node(&mut self, index: usize) -> usize {
         match self.selected_points[index] {
@@ -281,33 +881,113 @@ impl Editor {
                 self.selected_points[index] = Selected::Node(node_id);
                 node_id
             }
-            Selected::Selected(id) => self.ensure_node(id),
+            Selected::Previous(id) => self.ensure_node(id),
+        }
+    }
+    fn select_within_box(&mut self, start: Vec2, end: Vec2, op: SelectOp) {
+        let nodes = self.manager.nodes_in_box(start, end);
+        let rods = self.manager.rods_in_box(start, end, self.select_mode);
+        self.apply_area_selection(nodes, rods, op);
+    }
+    fn select_within_lasso(&mut self, op: SelectOp) {
+        let nodes = self.manager.nodes_in_polygon(&self.lasso);
+        let rods = self.manager.rods_in_polygon(&self.lasso, self.select_mode);
+        self.apply_area_selection(nodes, rods, op);
+    }
+    fn apply_area_selection(&mut self, nodes: Vec<usize>, rods: Vec<usize>, op: SelectOp) {
+        let targets = nodes
+            .into_iter()
+            .map(Selected::Node)
+            .chain(rods.into_iter().map(Selected::Rod));
+        for target in targets {
+            match op {
+                SelectOp::Add if !self.is_selected(&target) => self.selected_points.push(target),
+                SelectOp::Add => {}
+                SelectOp::Subtract => self.deselect(&target),
+                SelectOp::Toggle => self.toggle(target),
+            }
+        }
+    }
+    /// Selects whatever is under the cursor, clicking empty space in add mode selects a new point
+    fn select_at(&mut self, pos: Vec2, op: SelectOp) {
+        let target = self.selected(pos, THRESHOLD).map(|(sel, _)| sel);
+        match (op, target) {
+            (SelectOp::Add, target) => self
+                .selected_points
+                .push(target.unwrap_or(Selected::New(pos))),
+            (SelectOp::Subtract, Some(target)) => self.deselect(&target),
+            (SelectOp::Toggle, target) => self.toggle(target.unwrap_or(Selected::New(pos))),
+            (SelectOp::Subtract, None) => {}
         }
     }
-    fn select_within_box(&mut self, start: Vec2, end: Vec2) {
-        let min = start.min(end); // Top-left of the box
-        let max = start.max(end); // Bottom-right
 
-        //self.selected_points.clear(); // Optional: or merge with existing selection
+    /// Splits the selection into the existing nodes and rods it contains
+    fn selected_components(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut node_ids = Vec::new();
+        let mut rod_ids = Vec::new();
 
-        // Select nodes inside the box
-        for (i, node) in self.manager.c.nodes.iter().enumerate() {
-            if node.pos.x >= min.x
-                && node.pos.x <= max.x
-                && node.pos.y >= min.y
-                && node.pos.y <= max.y
-            {
-                self.selected_points.push(Selected::Node(i));
+        for sel in &self.selected_points {
+            match sel {
+                Selected::Node(id) => node_ids.push(*id),
+                Selected::Rod(id) => rod_ids.push(*id),
+                Selected::New(_) | Selected::Previous(_) => {} // Ignore
             }
         }
+        (node_ids, rod_ids)
+    }
+
+    /* Selection bookkeeping */
+    fn is_selected(&self, target: &Selected) -> bool {
+        matches!(target, Selected::Previous(_)) || self.selected_points.contains(target)
+    }
+    fn toggle(&mut self, target: Selected) {
+        if self.is_selected(&target) {
+            self.deselect(&target);
+        } else {
+            self.selected_points.push(target);
+        }
+    }
+    fn deselect(&mut self, target: &Selected) {
+        if let Selected::Previous(index) = target {
+            self.remove_selection(*index);
+            return;
+        }
+        while let Some(index) = self.selected_points.iter().position(|sel| sel == target) {
+            self.remove_selection(index);
+        }
+    }
+    /// Removes a selection entry, fixing up `Previous` references to later entries
+    /// and dropping the ones which referred to the removed entry
+    fn remove_selection(&mut self, index: usize) {
+        self.selected_points.remove(index);
 
-        // Select rods whose *midpoint* is inside the box
-        for (i, _) in self.manager.c.rods.iter().enumerate() {
-            let mid = self.manager.rod_midpoint(i);
-            if mid.x >= min.x && mid.x <= max.x && mid.y >= min.y && mid.y <= max.y {
-                self.selected_points.push(Selected::Rod(i));
+        let mut dangling = vec![];
+        for (i, sel) in self.selected_points.iter_mut().enumerate() {
+            if let Selected::Previous(j) = sel {
+                if *j == index {
+                    dangling.push(i);
+                } else if *j > index {
+                    *j -= 1;
+                }
             }
         }
+        for i in dangling.into_iter().rev() {
+            self.remove_selection(i);
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_stay_inside_the_crafts_folder() {
+        assert_eq!(name_problem("Truck 2"), None);
+        assert!(name_problem("").is_some());
+        assert!(name_problem("../settings").is_some());
+        assert!(name_problem("..").is_some());
+        assert!(name_problem("trucks/big").is_some());
+        assert!(name_problem("trucks\\big").is_some());
+    }
+}
@@ -0,0 +1,262 @@
+//! Vehicle Toolkit
+//!
+//! Key bindings scene, lists every action with its key and lets the user rebind them.
+//! Actions are listed under the input context they belong to.
+//!
+//! Clicking an action waits for the next key press, with any modifiers held, or middle click
+//! and binds it in place of the action's bindings. Shift clicking adds another binding instead.
+//! A modifier key is bound on its own when released without another key being pressed.
+//! Escape or a right click cancels, and Delete clears the action's bindings.
+//! Otherwise the quit or menu back bindings leave the screen, as does the Back button.
+//! Bindings shared by actions which can be active at the same time are highlighted. Every change is sent to the
+//! application straight away, which saves it to the settings file.
+use macroquad::{
+    prelude::{
+        KeyCode, MouseButton, Rect, Vec2, clear_background, draw_rectangle, draw_text,
+        get_keys_pressed, is_key_released, is_mouse_button_pressed, mouse_position, mouse_wheel,
+        screen_height,
+    },
+    ui::root_ui,
+};
+
+use super::Scene;
+use crate::{
+    AppMessage,
+    settings::{Action, Binding, InputContext, KeyBinds, Modifiers, is_modifier},
+    style::palette,
+};
+
+const ROW_HEIGHT: f32 = 30.0;
+const ROW_WIDTH: f32 = 720.0;
+/// Top left of the list of actions, before scrolling
+const LIST_START: Vec2 = Vec2::new(40.0, 120.0);
+/// Height of the title and buttons drawn over the top of the list
+const HEADER_HEIGHT: f32 = LIST_START.y - 10.0;
+const RESET_BUTTON: Vec2 = Vec2::new(40.0, 50.0);
+const BACK_BUTTON: Vec2 = Vec2::new(320.0, 50.0);
+/// Pixels scrolled per notch of the mouse wheel
+const SCROLL_SPEED: f32 = 40.0;
+
+/// A line of the list, either a context's heading or one of its actions
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    Heading(InputContext),
+    Action(Action),
+}
+
+pub struct KeyBindings {
+    key_binds: KeyBinds,
+    rows: Vec<Row>,
+    /// Action waiting for a key to be pressed
+    listening: Option<Action>,
+    /// Whether the next binding is added alongside the action's others
+    adding: bool,
+    /// Modifier key pressed while listening, bound if released on its own
+    pending_modifier: Option<KeyCode>,
+    scroll: f32,
+}
+impl Scene for KeyBindings {
+    fn update(&mut self, key_binds: &KeyBinds) -> AppMessage {
+        let mouse: Vec2 = mouse_position().into();
+
+        let list_height = self.rows.len() as f32 * ROW_HEIGHT;
+        let max_scroll = (LIST_START.y + list_height - screen_height() + ROW_HEIGHT).max(0.0);
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            self.scroll -= wheel.signum() * SCROLL_SPEED;
+        }
+        self.scroll = self.scroll.clamp(0.0, max_scroll);
+
+        // Buttons are shown while listening too, but only work once it's done
+        let reset = root_ui().button(RESET_BUTTON, "Reset to defaults");
+        let back = root_ui().button(BACK_BUTTON, "Back");
+
+        if let Some(action) = self.listening {
+            // Prefers the main key of a chord whose keys went down in the same frame
+            let pressed = get_keys_pressed();
+            let key = pressed
+                .iter()
+                .find(|&&key| !is_modifier(key))
+                .or(pressed.iter().next());
+            let binding = match key.copied() {
+                Some(KeyCode::Escape) => {
+                    self.stop_listening();
+                    return AppMessage::None;
+                }
+                Some(KeyCode::Delete) => {
+                    self.key_binds.unbind(action);
+                    self.stop_listening();
+                    return AppMessage::SetKeyBinds(self.key_binds.clone());
+                }
+                Some(key) if is_modifier(key) => {
+                    self.pending_modifier = Some(key);
+                    None
+                }
+                Some(key) => Some(Binding::chord(key, Modifiers::held())),
+                None => match self.pending_modifier {
+                    Some(key) if is_key_released(key) => Some(Binding::key(key)),
+                    _ if is_mouse_button_pressed(MouseButton::Middle) => {
+                        Some(Binding::Mouse(MouseButton::Middle))
+                    }
+                    _ => None,
+                },
+            };
+            if is_mouse_button_pressed(MouseButton::Right) {
+                self.stop_listening();
+            } else if let Some(binding) = binding {
+                if self.adding {
+                    self.key_binds.add_binding(action, binding);
+                } else {
+                    self.key_binds.bind(action, binding);
+                }
+                self.stop_listening();
+                return AppMessage::SetKeyBinds(self.key_binds.clone());
+            }
+            return AppMessage::None;
+        }
+
+        if back || key_binds.is_key_pressed(Action::MenuBack) {
+            return AppMessage::PopScene;
+        }
+        if reset {
+            self.key_binds = KeyBinds::default();
+            return AppMessage::SetKeyBinds(self.key_binds.clone());
+        }
+        if is_mouse_button_pressed(MouseButton::Left)
+            && mouse.y > HEADER_HEIGHT
+            && !root_ui().is_mouse_over(mouse)
+        {
+            self.listening = self
+                .rows
+                .iter()
+                .enumerate()
+                .find(|&(i, _)| self.row_rect(i).contains(mouse))
+                .and_then(|(_, row)| match row {
+                    Row::Action(action) => Some(*action),
+                    Row::Heading(_) => None,
+                });
+            self.adding = Modifiers::held().shift;
+        }
+        AppMessage::None
+    }
+
+    fn draw(&self) {
+        let palette = palette();
+        clear_background(palette.background);
+
+        let conflicts = self.key_binds.conflicts();
+        for (i, &line) in self.rows.iter().enumerate() {
+            let row = self.row_rect(i);
+            if row.bottom() < HEADER_HEIGHT || row.y > screen_height() {
+                continue;
+            }
+            let action = match line {
+                Row::Action(action) => action,
+                Row::Heading(context) => {
+                    let text_y = row.y + ROW_HEIGHT * 0.8;
+                    draw_text(&format!("{context:?}"), row.x, text_y, 28.0, palette.text);
+                    continue;
+                }
+            };
+            let conflicting = conflicts
+                .iter()
+                .any(|(_, actions)| actions.contains(&action));
+            let bindings = self.key_binds.bindings(action);
+            if self.listening == Some(action) {
+                draw_rectangle(row.x, row.y, row.w, row.h, palette.highlight);
+            } else if i % 2 == 0 {
+                draw_rectangle(row.x, row.y, row.w, row.h, palette.panel);
+            }
+
+            let key = if self.listening == Some(action) {
+                "Press a key...".to_string()
+            } else if bindings.is_empty() {
+                "Unbound".to_string()
+            } else {
+                bindings
+                    .iter()
+                    .map(Binding::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let color = if conflicting {
+                palette.warning
+            } else {
+                palette.text
+            };
+            let text_y = row.y + ROW_HEIGHT * 0.7;
+            draw_text(&action.name(), row.x + 8.0, text_y, 24.0, palette.text);
+            draw_text(&key, row.x + 280.0, text_y, 24.0, color);
+        }
+
+        // Drawn over the list so rows scroll underneath
+        draw_rectangle(
+            0.0,
+            0.0,
+            ROW_WIDTH + 2.0 * LIST_START.x,
+            HEADER_HEIGHT,
+            palette.background,
+        );
+        draw_text("Key bindings", 40.0, 34.0, 32.0, palette.text);
+        if !conflicts.is_empty() {
+            let message = format!(
+                "{} bindings are shared by actions active together",
+                conflicts.len()
+            );
+            draw_text(&message, 240.0, 34.0, 24.0, palette.warning);
+        }
+    }
+
+    fn context(&self) -> InputContext {
+        InputContext::Menu
+    }
+
+    /// Goes back to the menu, the quit binding only reaches here when not listening
+    fn on_quit(&mut self) -> AppMessage {
+        match self.listening {
+            Some(_) => AppMessage::None,
+            None => AppMessage::PopScene,
+        }
+    }
+    /// Keys pressed while waiting for a binding are bound rather than acted on
+    fn captures_keys(&self) -> bool {
+        self.listening.is_some()
+    }
+}
+impl KeyBindings {
+    pub fn new(key_binds: KeyBinds) -> Self {
+        let mut rows = vec![];
+        for context in InputContext::ALL {
+            rows.push(Row::Heading(context));
+            rows.extend(
+                Action::ALL
+                    .into_iter()
+                    .filter(|action| action.context() == context)
+                    .map(Row::Action),
+            );
+        }
+        Self {
+            key_binds,
+            rows,
+            listening: None,
+            adding: false,
+            pending_modifier: None,
+            scroll: 0.0,
+        }
+    }
+
+    fn stop_listening(&mut self) {
+        self.listening = None;
+        self.pending_modifier = None;
+    }
+
+    /// Screen area of the `i`th row
+    fn row_rect(&self, i: usize) -> Rect {
+        Rect::new(
+            LIST_START.x,
+            LIST_START.y + i as f32 * ROW_HEIGHT - self.scroll,
+            ROW_WIDTH,
+            ROW_HEIGHT,
+        )
+    }
+}
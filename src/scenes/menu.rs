@@ -1,25 +1,284 @@
-use super::Scene;
+//! Vehicle Toolkit
+//!
+//! Main menu scene, the first thing shown when the app starts.
+//!
+//! The menu is a list of buttons drawn with the application skin. They can be clicked, or
+//! moved between with the menu actions and activated, so it works from a keyboard or gamepad.
+//! Opening the craft library or the levels replaces the list with one entry per saved file.
+use std::{fs, path::PathBuf};
+
+use macroquad::{
+    prelude::{clear_background, draw_text, measure_text, screen_height, screen_width, vec2},
+    ui::{hash, root_ui},
+};
+
+use super::{Scene, editor::Editor, key_bindings::KeyBindings, simulation::Simulation};
 use crate::{
     AppMessage,
-    settings::{Action, KeyBinds},
+    craft::Craft,
+    settings::{Action, InputContext, KeyBinds},
+    style::palette,
+    ui::modal::{Answer, Dialog, Purpose},
 };
-use macroquad::prelude::{BLUE, draw_rectangle};
 
-pub struct Menu {}
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Folders the craft library and levels are read from
+const CRAFTS_PATH: &str = "data/crafts";
+const LEVELS_PATH: &str = "data/levels";
+const BUTTON_SPACING: f32 = 64.0;
+
+/// A list of buttons shown by the menu
+#[derive(Debug, Clone, PartialEq)]
+enum Page {
+    Main,
+    /// Saved crafts, opened in the editor
+    Library(Vec<PathBuf>),
+    /// Saved levels, opened straight into the simulation
+    Levels(Vec<PathBuf>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MainButton {
+    NewCraft,
+    OpenCraft,
+    Levels,
+    Settings,
+    Quit,
+}
+impl MainButton {
+    const ALL: [MainButton; 5] = [
+        MainButton::NewCraft,
+        MainButton::OpenCraft,
+        MainButton::Levels,
+        MainButton::Settings,
+        MainButton::Quit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            MainButton::NewCraft => "New Craft",
+            MainButton::OpenCraft => "Open Craft",
+            MainButton::Levels => "Levels",
+            MainButton::Settings => "Settings",
+            MainButton::Quit => "Quit",
+        }
+    }
+}
+
+pub struct Menu {
+    page: Page,
+    /// Index of the button selected with the keyboard or gamepad
+    focus: usize,
+    /// Problem to show the user, like a file that couldn't be opened
+    message: Option<String>,
+    /// Whether the user has been asked about autosaved work yet
+    checked_recovery: bool,
+}
 impl Scene for Menu {
     fn update(&mut self, key_binds: &KeyBinds) -> AppMessage {
+        if !self.checked_recovery {
+            self.checked_recovery = true;
+            if Editor::has_recovery() {
+                return AppMessage::ShowDialog(
+                    Dialog::confirm(
+                        Purpose::Recover,
+                        "Recover",
+                        "A craft wasn't saved last time, recover it?",
+                        "Recover",
+                    )
+                    .or_deny("Discard"),
+                );
+            }
+        }
+        let labels = self.labels();
+
+        if key_binds.is_key_pressed(Action::MenuUp) {
+            self.focus = (self.focus + labels.len() - 1) % labels.len();
+        }
+        if key_binds.is_key_pressed(Action::MenuDown) {
+            self.focus = (self.focus + 1) % labels.len();
+        }
+        let mut chosen = key_binds
+            .is_key_pressed(Action::MenuSelect)
+            .then_some(self.focus);
+        if key_binds.is_key_pressed(Action::MenuBack) && self.page != Page::Main {
+            self.open_page(Page::Main);
+            return AppMessage::None;
+        }
+
+        // Shortcuts kept from before the menu had buttons
         if key_binds.is_key_pressed(Action::SwitchScene) {
-            return AppMessage::OpenEditor(None);
+            return AppMessage::PushScene(Box::new(Editor::new()));
+        }
+        if key_binds.is_key_pressed(Action::OpenSettings) {
+            return AppMessage::PushScene(Box::new(KeyBindings::new(key_binds.clone())));
+        }
+
+        let size = vec2(420.0, 120.0 + labels.len() as f32 * BUTTON_SPACING);
+        let pos = vec2(
+            screen_width() / 2.0 - size.x / 2.0,
+            screen_height() / 2.0 - size.y / 2.0,
+        );
+        root_ui().window(hash!("main_menu", labels.len()), pos, size, |ui| {
+            for (i, label) in labels.iter().enumerate() {
+                let y = 20.0 + i as f32 * BUTTON_SPACING;
+                if i == self.focus {
+                    ui.label(vec2(10.0, y + 8.0), ">");
+                }
+                if ui.button(vec2(40.0, y), label.as_str()) {
+                    chosen = Some(i);
+                }
+            }
+        });
+
+        match chosen {
+            Some(i) => {
+                self.focus = i;
+                self.activate(i, key_binds)
+            }
+            None => AppMessage::None,
         }
-        AppMessage::None
     }
 
     fn draw(&self) {
-        draw_rectangle(0.0, 0.0, 50.0, 50.0, BLUE);
+        let palette = palette();
+        clear_background(palette.background);
+
+        let title = "Vehicle Toolkit";
+        let width = measure_text(title, None, 48, 1.0).width;
+        draw_text(
+            title,
+            (screen_width() - width) / 2.0,
+            80.0,
+            48.0,
+            palette.text,
+        );
+
+        if let Some(message) = &self.message {
+            let width = measure_text(message, None, 24, 1.0).width;
+            let x = (screen_width() - width) / 2.0;
+            draw_text(message, x, screen_height() - 40.0, 24.0, palette.text);
+        }
+        let version = format!("v{VERSION}");
+        draw_text(&version, 10.0, screen_height() - 10.0, 20.0, palette.text);
+    }
+
+    fn context(&self) -> InputContext {
+        InputContext::Menu
+    }
+
+    /// Lists the files again, one may have been saved while the menu was covered
+    fn on_resume(&mut self, _popped: &dyn Scene) {
+        let page = match self.page {
+            Page::Main => return,
+            Page::Library(_) => Page::Library(json_files(CRAFTS_PATH)),
+            Page::Levels(_) => Page::Levels(json_files(LEVELS_PATH)),
+        };
+        let focus = self.focus;
+        self.open_page(page);
+        self.focus = focus.min(self.labels().len() - 1);
+    }
+
+    fn on_dialog(&mut self, purpose: Purpose, answer: Answer) -> AppMessage {
+        match (purpose, answer) {
+            (Purpose::Recover, Answer::Confirmed) => match Editor::recover() {
+                Ok(editor) => return AppMessage::PushScene(Box::new(editor)),
+                Err(err) => self.message = Some(format!("Couldn't recover craft: {err}")),
+            },
+            (Purpose::Recover, Answer::Denied) => {
+                if let Err(err) = Editor::discard_recovery() {
+                    self.message = Some(format!("Couldn't remove recovery file: {err}"));
+                }
+            }
+            _ => (),
+        }
+        AppMessage::None
     }
 }
 impl Menu {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            page: Page::Main,
+            focus: 0,
+            message: None,
+            checked_recovery: false,
+        }
+    }
+
+    fn open_page(&mut self, page: Page) {
+        self.message = match &page {
+            Page::Library(files) if files.is_empty() => {
+                Some(format!("No crafts saved in {CRAFTS_PATH} yet"))
+            }
+            Page::Levels(files) if files.is_empty() => {
+                Some(format!("No levels saved in {LEVELS_PATH} yet"))
+            }
+            _ => None,
+        };
+        self.page = page;
+        self.focus = 0;
+    }
+
+    /// Text of each button on the current page
+    fn labels(&self) -> Vec<String> {
+        match &self.page {
+            Page::Main => MainButton::ALL
+                .iter()
+                .map(|button| button.label().to_string())
+                .collect(),
+            Page::Library(files) | Page::Levels(files) => files
+                .iter()
+                .map(|path| file_name(path))
+                .chain(["Back".to_string()])
+                .collect(),
+        }
+    }
+
+    /// Does whatever the `i`th button of the current page does
+    fn activate(&mut self, i: usize, key_binds: &KeyBinds) -> AppMessage {
+        match self.page.clone() {
+            Page::Main => match MainButton::ALL[i] {
+                MainButton::NewCraft => return AppMessage::PushScene(Box::new(Editor::new())),
+                MainButton::OpenCraft => self.open_page(Page::Library(json_files(CRAFTS_PATH))),
+                MainButton::Levels => self.open_page(Page::Levels(json_files(LEVELS_PATH))),
+                MainButton::Settings => {
+                    return AppMessage::PushScene(Box::new(KeyBindings::new(key_binds.clone())));
+                }
+                MainButton::Quit => return AppMessage::Quit,
+            },
+            Page::Library(files) | Page::Levels(files) if i == files.len() => {
+                self.open_page(Page::Main)
+            }
+            Page::Library(files) => match Craft::load(files[i].clone()) {
+                Ok(craft) => {
+                    let editor = Editor::edit_file(craft, files[i].clone());
+                    return AppMessage::PushScene(Box::new(editor));
+                }
+                Err(err) => self.message = Some(format!("Couldn't open craft: {err}")),
+            },
+            Page::Levels(files) => match Craft::load(files[i].clone()) {
+                Ok(craft) => return AppMessage::PushScene(Box::new(Simulation::new(craft))),
+                Err(err) => self.message = Some(format!("Couldn't open level: {err}")),
+            },
+        }
+        AppMessage::None
     }
 }
+
+/// Every JSON file in a folder, sorted by name, empty if the folder can't be read
+fn json_files(folder: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return vec![];
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_stem()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
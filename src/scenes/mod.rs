@@ -1,14 +1,75 @@
+//! Vehicle Toolkit
 //!
+//! Scenes are the top level windows of the app, the menu, editor and simulation
 //!
+//! The application keeps a stack of scenes. Only the top one is updated, scenes underneath
+//! keep their state until it is popped and they are resumed. Overlays, like the pause menu,
+//! are drawn over the scenes below them instead of hiding them.
+pub mod camera;
 pub mod editor;
+pub mod key_bindings;
 pub mod menu;
+pub mod pause;
 pub mod simulation;
 
-use crate::{AppMessage, settings::KeyBinds};
+use std::io;
+
+use crate::{
+    AppMessage,
+    craft::Craft,
+    settings::{InputContext, KeyBinds},
+    ui::modal::{Answer, Purpose},
+};
 
 /// Definition of a basic window for the program,
 /// The application manages switching between these scenes.
 pub trait Scene {
     fn update(&mut self, key_binds: &KeyBinds) -> AppMessage;
     fn draw(&self);
+    /// Which actions respond to input while the scene is open, besides the global ones
+    fn context(&self) -> InputContext;
+    /// Whether the scenes underneath are drawn before this one
+    fn is_overlay(&self) -> bool {
+        false
+    }
+    /// The craft the scene is working on, if any
+    fn craft(&self) -> Option<&Craft> {
+        None
+    }
+
+    /* Lifecycle */
+    /// Called when the scene is put on the stack
+    fn on_enter(&mut self) {}
+    /// Called when the scene is taken off the stack, a dialog it returns is still shown
+    fn on_exit(&mut self) -> AppMessage {
+        AppMessage::None
+    }
+    /// Called when another scene is pushed over this one
+    fn on_pause(&mut self) {}
+    /// Called when this scene is back on top, with the scene that was popped off it
+    fn on_resume(&mut self, _popped: &dyn Scene) {}
+
+    /// Called with the answer to a dialog the scene asked for
+    fn on_dialog(&mut self, _purpose: Purpose, _answer: Answer) -> AppMessage {
+        AppMessage::None
+    }
+    /// Called instead of `update` when the quit binding is pressed, asks to quit unless the
+    /// scene uses it to go back
+    fn on_quit(&mut self) -> AppMessage {
+        AppMessage::Quit
+    }
+    /// Whether the scene is reading key presses itself, so bindings like quit shouldn't fire
+    fn captures_keys(&self) -> bool {
+        false
+    }
+
+    /* Saving */
+    /// Whether there is work which would be lost if the app closed now
+    fn has_unsaved_changes(&self) -> bool {
+        false
+    }
+    /// Saves the scene's work, asked for before quitting with unsaved changes
+    fn save(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,67 @@
+//! Vehicle Toolkit
+//!
+//! Pause menu, an overlay pushed over the simulation which stops it until resumed.
+//! The quit or menu back bindings resume, the simulation underneath keeps its state.
+use macroquad::{
+    prelude::{Color, draw_rectangle, screen_height, screen_width, vec2},
+    ui::{hash, root_ui},
+};
+
+use super::{Scene, key_bindings::KeyBindings};
+use crate::{
+    AppMessage,
+    settings::{Action, InputContext, KeyBinds},
+};
+
+/// Darkens the scene underneath
+const SHADE_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.4);
+const BUTTON_SPACING: f32 = 64.0;
+
+pub struct PauseMenu;
+impl Scene for PauseMenu {
+    fn update(&mut self, key_binds: &KeyBinds) -> AppMessage {
+        if key_binds.is_key_pressed(Action::MenuBack) {
+            return AppMessage::PopScene;
+        }
+
+        let mut message = AppMessage::None;
+        let size = vec2(360.0, 40.0 + 3.0 * BUTTON_SPACING);
+        let pos = vec2(
+            screen_width() / 2.0 - size.x / 2.0,
+            screen_height() / 2.0 - size.y / 2.0,
+        );
+        root_ui().window(hash!(), pos, size, |ui| {
+            if ui.button(vec2(40.0, 20.0), "Resume") {
+                message = AppMessage::PopScene;
+            }
+            if ui.button(vec2(40.0, 20.0 + BUTTON_SPACING), "Key Bindings") {
+                message = AppMessage::PushScene(Box::new(KeyBindings::new(key_binds.clone())));
+            }
+            if ui.button(vec2(40.0, 20.0 + 2.0 * BUTTON_SPACING), "Quit") {
+                message = AppMessage::Quit;
+            }
+        });
+        message
+    }
+
+    fn draw(&self) {
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), SHADE_COLOR);
+    }
+
+    fn context(&self) -> InputContext {
+        InputContext::Menu
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn on_quit(&mut self) -> AppMessage {
+        AppMessage::PopScene
+    }
+}
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self
+    }
+}
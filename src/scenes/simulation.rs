@@ -1,114 +1,517 @@
 //! Vehicle Toolkit
 //! Simulation Scene
 use macroquad::prelude::{
-    GREEN, Vec2, WHITE, clear_background, draw_line, get_frame_time, get_time, screen_width,
+    BLUE, Color, DARKBLUE, GRAY, GREEN, MouseButton, PURPLE, RED, Vec2, clear_background,
+    draw_circle_lines, draw_line, draw_rectangle, draw_text, get_frame_time, is_mouse_button_down,
+    is_mouse_button_pressed, mouse_position, screen_width, set_camera, set_default_camera, vec2,
 };
+use macroquad::ui::{hash, root_ui};
 
-use super::Scene;
+use super::{Scene, camera::SceneCamera, pause::PauseMenu};
 use crate::{
     AppMessage,
-    settings::{Action, KeyBinds},
+    settings::{Action, InputContext, KeyBinds},
+    style::palette,
+    ui::modal::Dialog,
 };
 
 use crate::craft::*;
-use crate::craft::{components::*, draw_craft};
+use crate::physics::environment::Wind;
+use crate::physics::joints::CHANNELS;
+use crate::physics::settings::PhysicsSettings;
+use crate::physics::world::{Grab, Placement, World};
+use crate::ui::{Panel, Widget};
 
 const FLOOR: f32 = 600.0;
+/// Physics settings used when the craft doesn't bring its own
+const PHYSICS_PATH: &str = "data/physics.json";
+const WATER_COLOR: Color = Color::new(0.2, 0.45, 0.9, 0.35);
+/// Furthest apart two nodes can be to be hitched together
+const HITCH_DISTANCE: f32 = 40.0;
+/// How quickly the camera catches up with the craft, higher is snappier
+const FOLLOW_RATE: f32 = 4.0;
+/// Speeds the simulation can be run at, cycled through with `SlowDown` and `SpeedUp`
+const TIME_SCALES: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 4.0];
+const DEFAULT_TIME_SCALE: usize = 3;
+/// Most physics steps taken in one frame, so a slow frame can't snowball into slower ones
+const MAX_STEPS_PER_FRAME: usize = 16;
+/// Angle the spawn point turns by per key press
+const SPAWN_ROTATION_STEP: f32 = std::f32::consts::PI / 12.0;
+/// Strain at which the stress overlay reaches its strongest colour
+const MAX_STRAIN: f32 = 0.05;
+const COMPRESSION_COLOR: Color = BLUE;
+const TENSION_COLOR: Color = RED;
+/// Seconds of travel shown by a velocity vector
+const VELOCITY_SCALE: f32 = 0.1;
+/// How close in screen pixels the mouse has to be to grab a node
+const GRAB_DISTANCE: f32 = 20.0;
+/// Key binds driving each control channel backwards and forwards
+const CHANNEL_ACTIONS: [(Action, Action); CHANNELS] = [
+    (Action::Channel1Reverse, Action::Channel1Forward),
+    (Action::Channel2Reverse, Action::Channel2Forward),
+    (Action::Channel3Reverse, Action::Channel3Forward),
+];
 
 pub struct Simulation {
-    gravity: Vec2,
     original_craft: Craft,
-    craft: Craft,
+    /// Where the craft is placed when the simulation is reset
+    spawn: Placement,
+    world: World,
+    /// Snapshot of the world to quickly restart from
+    checkpoint: Option<World>,
+    camera: SceneCamera,
+    /// Whether the camera tracks the craft or is moved freely
+    follow: bool,
+    paused: bool,
+    /// Index into `TIME_SCALES`
+    time_scale: usize,
+    /// Scaled time not yet simulated, always less than a fixed step after updating
+    accumulator: f32,
+    show_stress: bool,
+    show_velocities: bool,
+    show_physics_panel: bool,
+    physics_panel: Panel<PhysicsEdit>,
 }
 impl Scene for Simulation {
     fn update(&mut self, key_binds: &KeyBinds) -> AppMessage {
-        // Changes scene to Editor
+        // Goes back to the scene the simulation was opened from
         if key_binds.is_key_pressed(Action::SwitchScene) {
-            return AppMessage::OpenEditor(Some(self.original_craft.clone()));
+            return AppMessage::PopScene;
         }
 
-        let dt = get_frame_time() as f32;
+        let mouse = self.camera.screen_to_world(mouse_position().into());
 
-        // Verlet integration
-        for node in &mut self.craft.nodes {
-            let temp = node.pos;
-            let velocity = node.pos - node.prev_pos;
-            node.pos += velocity + self.gravity * dt * dt;
-            node.prev_pos = temp;
+        if key_binds.is_key_pressed(Action::TogglePhysicsPanel) {
+            self.show_physics_panel = !self.show_physics_panel;
+        }
+        if self.show_physics_panel {
+            let mut edit = PhysicsEdit {
+                settings: self.world.settings,
+                save_default: false,
+                save_craft: false,
+            };
+            let size = vec2(340.0, 370.0);
+            let pos = vec2(screen_width() - size.x - 20.0, 100.0);
+            self.physics_panel
+                .show(hash!("physics_panel"), pos, size, &mut edit);
 
-            // Floor collision
-            if node.pos.y > FLOOR {
-                node.pos.y = FLOOR;
-                let mut velocity = node.pos - node.prev_pos;
-                velocity.y *= -0.3;
-                node.prev_pos = node.pos - velocity;
+            self.world.settings = edit.settings;
+            if edit.save_craft {
+                self.original_craft.physics = Some(edit.settings);
+            }
+            if edit.save_default
+                && let Err(err) = edit.settings.save(PHYSICS_PATH.into())
+            {
+                let message = format!("Couldn't save physics settings: {err}");
+                return AppMessage::ShowDialog(Dialog::error("Error", &message));
             }
         }
 
-        // Rod constraints
-        for _ in 0..5 {
-            for rod in self.craft.rods.clone().iter() {
-                let (a, b) = (rod.node_a, rod.node_b);
-                let (pa, pb) = (self.craft.nodes[a].pos, self.craft.nodes[b].pos);
-                let delta = pb - pa;
-                let dist = delta.length();
-                if dist == 0.0 {
-                    continue;
-                }
-                let dir = delta / dist;
-
-                match rod.rod_type {
-                    RodType::SOLID => {
-                        let diff = dist - rod.length;
-                        let correction = dir * (diff * 0.5);
-                        self.move_nodes(a, b, correction);
-                    }
-                    RodType::ROPE => {
-                        if dist > rod.length {
-                            let diff = dist - rod.length;
-                            let correction = dir * (diff * 0.5);
-                            self.move_nodes(a, b, correction);
-                        }
-                    }
-                    RodType::SPRING => {
-                        // Simple spring: pull or push nodes toward rest length
-                        let rest_length = 100.0;
-                        let k = 0.2;
-                        let force = dir * (dist - rest_length) * k;
-                        self.move_nodes(a, b, force);
-                    }
-                    RodType::PISTON => {
-                        // Dynamic length, could be user-controlled or animated
-                        // For now, just placeholder behavior
-                        let desired_length = 150.0 + 50.0 * (get_time() as f32).sin();
-                        let diff = dist - desired_length;
-                        let correction = dir * (diff * 0.5);
-                        self.move_nodes(a, b, correction);
-                    }
+        // Dragging nodes around with the mouse
+        if is_mouse_button_pressed(MouseButton::Left)
+            && !root_ui().is_mouse_over(mouse_position().into())
+        {
+            let radius = self.camera.pixels_to_world(GRAB_DISTANCE);
+            self.world.grab = self.world.nearest_node(mouse, radius).map(|node| Grab {
+                node,
+                target: mouse,
+            });
+        }
+        if is_mouse_button_down(MouseButton::Left) {
+            if let Some(grab) = &mut self.world.grab {
+                grab.target = mouse;
+            }
+        } else {
+            self.world.grab = None;
+        }
+
+        // Drops another copy of the craft centred on the mouse
+        if key_binds.is_key_pressed(Action::SpawnCraft) {
+            let offset = mouse - self.original_craft.centre();
+            self.world
+                .spawn(&self.original_craft, Placement::new(offset, 0.0));
+        }
+
+        // Restarting
+        if key_binds.is_key_pressed(Action::SetSpawn) {
+            self.spawn.offset = mouse - self.original_craft.centre();
+        }
+        if key_binds.is_key_pressed(Action::RotateSpawnLeft) {
+            self.spawn.angle -= SPAWN_ROTATION_STEP;
+        }
+        if key_binds.is_key_pressed(Action::RotateSpawnRight) {
+            self.spawn.angle += SPAWN_ROTATION_STEP;
+        }
+        if key_binds.is_key_pressed(Action::ResetSimulation) {
+            self.reset();
+        }
+        if key_binds.is_key_pressed(Action::SaveCheckpoint) {
+            self.checkpoint = Some(self.world.clone());
+        }
+        if key_binds.is_key_pressed(Action::LoadCheckpoint)
+            && let Some(checkpoint) = &self.checkpoint
+        {
+            self.world = checkpoint.clone();
+            self.accumulator = 0.0;
+            self.sync_physics_panel();
+        }
+
+        // Detaches every hitch, or hitches the closest pair of crafts
+        if key_binds.is_key_pressed(Action::ToggleHitch) {
+            if self.world.hitches.is_empty() {
+                if let Some((a, b)) = self.world.nearest_hitch_pair(HITCH_DISTANCE) {
+                    self.world.hitch(a, b);
                 }
+            } else {
+                while self.world.detach(0).is_some() {}
+            }
+        }
+
+        // Overlays
+        if key_binds.is_key_pressed(Action::ToggleStress) {
+            self.show_stress = !self.show_stress;
+        }
+        if key_binds.is_key_pressed(Action::ToggleVelocities) {
+            self.show_velocities = !self.show_velocities;
+        }
+
+        if key_binds.is_key_pressed(Action::ToggleWind) {
+            self.world.environment.wind = if self.world.environment.wind == Wind::default() {
+                self.world.settings.wind
+            } else {
+                Wind::default()
+            };
+        }
+
+        // Craft controls
+        for (channel, (reverse, forward)) in CHANNEL_ACTIONS.into_iter().enumerate() {
+            let mut input = 0.0;
+            if key_binds.is_key_down(reverse) {
+                input -= 1.0;
             }
+            if key_binds.is_key_down(forward) {
+                input += 1.0;
+            }
+            self.world.controls[channel] = input;
+        }
+
+        // Time controls
+        if key_binds.is_key_pressed(Action::TogglePause) {
+            self.paused = !self.paused;
+            self.accumulator = 0.0;
         }
+        if key_binds.is_key_pressed(Action::SlowDown) {
+            self.time_scale = self.time_scale.saturating_sub(1);
+        }
+        if key_binds.is_key_pressed(Action::SpeedUp) {
+            self.time_scale = (self.time_scale + 1).min(TIME_SCALES.len() - 1);
+        }
+
+        let dt = get_frame_time();
+        if self.paused {
+            if key_binds.is_key_pressed(Action::StepFrame) {
+                self.world.step(World::FIXED_DT);
+            }
+        } else {
+            self.accumulator += dt * TIME_SCALES[self.time_scale];
+            let mut steps = 0;
+            while self.accumulator >= World::FIXED_DT && steps < MAX_STEPS_PER_FRAME {
+                self.world.step(World::FIXED_DT);
+                self.accumulator -= World::FIXED_DT;
+                steps += 1;
+            }
+            // Drop time the solver couldn't keep up with rather than carrying it over
+            self.accumulator = self.accumulator.min(World::FIXED_DT);
+        }
+
+        // Camera, panning by hand lets go of the craft
+        if key_binds.is_key_pressed(Action::ToggleCameraFollow) {
+            self.follow = !self.follow;
+        }
+        if self.camera.pan_input(key_binds) {
+            self.follow = false;
+        }
+        self.camera.zoom_input(key_binds);
+        if self.follow
+            && let Some(craft) = self.world.crafts.first()
+            && !craft.nodes.is_empty()
+        {
+            let smoothing = 1.0 - (-FOLLOW_RATE * dt).exp();
+            self.camera.target = self.camera.target.lerp(craft.centre(), smoothing);
+        }
+
         AppMessage::None
     }
 
     fn draw(&self) {
-        clear_background(WHITE);
-        draw_craft(&self.craft);
-        draw_line(0.0, 600.0, screen_width(), 600.0, 2.0, GREEN);
+        let palette = palette();
+        clear_background(palette.background);
+        set_camera(&*self.camera);
+
+        for water in &self.world.environment.water {
+            let area = water.area;
+            draw_rectangle(area.x, area.y, area.w, area.h, WATER_COLOR);
+            draw_line(area.x, area.y, area.right(), area.y, 2.0, DARKBLUE);
+        }
+
+        for craft in &self.world.crafts {
+            draw_craft(craft);
+            if self.show_stress {
+                self.draw_stress(craft);
+            }
+            if self.show_velocities {
+                draw_velocities(craft, self.world.substep_dt());
+            }
+        }
+        for hitch in &self.world.hitches {
+            let (a, b) = (self.world.node(hitch.a), self.world.node(hitch.b));
+            draw_line(a.x, a.y, b.x, b.y, 3.0, PURPLE);
+        }
+        if let Some(grab) = &self.world.grab {
+            let node = self.world.node(grab.node);
+            draw_line(
+                node.x,
+                node.y,
+                grab.target.x,
+                grab.target.y,
+                1.0,
+                palette.text,
+            );
+            draw_circle_lines(grab.target.x, grab.target.y, 4.0, 1.0, palette.text);
+        }
+        let view = self.camera.visible_rect();
+        draw_line(view.left(), FLOOR, view.right(), FLOOR, 2.0, GREEN);
+
+        // Spawn point, pointing along the craft's rotated x axis
+        let centre = self.original_craft.centre();
+        let spawn = self.spawn.apply(centre, centre);
+        let facing = spawn + Vec2::from_angle(self.spawn.angle) * 30.0;
+        draw_circle_lines(spawn.x, spawn.y, 10.0, 2.0, palette.highlight);
+        draw_line(spawn.x, spawn.y, facing.x, facing.y, 2.0, palette.highlight);
+
+        set_default_camera();
+        let mode = if self.follow {
+            "Camera: follow"
+        } else {
+            "Camera: free"
+        };
+        draw_text(mode, 10.0, 30.0, 24.0, palette.text);
+
+        let speed = if self.paused {
+            "Paused".to_string()
+        } else {
+            format!("Speed: x{}", TIME_SCALES[self.time_scale])
+        };
+        draw_text(&speed, 10.0, 54.0, 24.0, palette.text);
+
+        let wind = self.world.environment.wind.velocity;
+        if wind != Vec2::ZERO {
+            draw_text(
+                &format!("Wind: {:.0}", wind.x),
+                10.0,
+                78.0,
+                24.0,
+                palette.text,
+            );
+        }
+
+        if self.checkpoint.is_some() {
+            draw_text("Checkpoint saved", 10.0, 102.0, 24.0, palette.text);
+        }
+
+        let locked = self.world.locked_hinges();
+        if locked > 0 {
+            let message = format!("{locked} hinge(s) locked, their arms are joined elsewhere");
+            draw_text(&message, 10.0, 126.0, 24.0, palette.warning);
+        }
+
+        if self.show_stress {
+            draw_stress_legend();
+        }
+    }
+
+    fn context(&self) -> InputContext {
+        InputContext::Simulation
+    }
+
+    fn craft(&self) -> Option<&Craft> {
+        Some(&self.original_craft)
+    }
+
+    /// Opens the pause menu, which has its own way to quit
+    fn on_quit(&mut self) -> AppMessage {
+        AppMessage::PushScene(Box::new(PauseMenu::new()))
     }
 }
 impl Simulation {
     pub fn new(craft: Craft) -> Self {
+        let spawn = Placement::default();
+        let world = Self::build_world(&craft, spawn);
         Self {
-            gravity: Vec2::new(0.0, 500.0),
-            original_craft: craft.clone(),
-            craft,
+            physics_panel: physics_panel(&world.settings),
+            world,
+            original_craft: craft,
+            spawn,
+            checkpoint: None,
+            camera: SceneCamera::y_down(),
+            follow: true,
+            paused: false,
+            time_scale: DEFAULT_TIME_SCALE,
+            accumulator: 0.0,
+            show_stress: false,
+            show_velocities: false,
+            show_physics_panel: false,
         }
     }
 
-    fn move_nodes(&mut self, a: usize, b: usize, correction: Vec2) {
-        self.craft.nodes[a].pos += correction;
+    /// Physics for a craft, its own settings if it has them, otherwise the saved defaults
+    fn physics_settings(craft: &Craft) -> PhysicsSettings {
+        craft
+            .physics
+            .unwrap_or_else(|| PhysicsSettings::load(PHYSICS_PATH.into()).unwrap_or_default())
+    }
+
+    fn build_world(craft: &Craft, spawn: Placement) -> World {
+        let mut world = World::new(Self::physics_settings(craft), FLOOR);
+        world.environment.water.extend(world.settings.pool);
+        world.spawn(craft, spawn);
+        world
+    }
+
+    /// Colours every rod by its strain, over the top of the normal rod colours
+    fn draw_stress(&self, craft: &Craft) {
+        for rod in &craft.rods {
+            let (a, b) = (craft.nodes[rod.node_a].pos, craft.nodes[rod.node_b].pos);
+            let color = strain_color(self.world.strain(craft, rod));
+            draw_line(a.x, a.y, b.x, b.y, 3.0, color);
+        }
+    }
+
+    /// Rebuilds the world from the original craft at the spawn point,
+    /// keeping the weather and physics settings
+    fn reset(&mut self) {
+        let environment = self.world.environment.clone();
+        let settings = self.world.settings;
+        self.world = Self::build_world(&self.original_craft, self.spawn);
+        self.world.environment = environment;
+        self.world.settings = settings;
+        self.accumulator = 0.0;
+        self.sync_physics_panel();
+    }
+
+    /// Rebuilds the physics panel so its sliders show the settings the world is running with
+    fn sync_physics_panel(&mut self) {
+        self.physics_panel = physics_panel(&self.world.settings);
+    }
+}
+
+/// What the physics panel edits, applied to the world after the panel is shown
+struct PhysicsEdit {
+    settings: PhysicsSettings,
+    save_default: bool,
+    save_craft: bool,
+}
+
+/// Sliders changing the physics of the running world, starting from `settings`
+fn physics_panel(settings: &PhysicsSettings) -> Panel<PhysicsEdit> {
+    Panel::titled("Physics")
+        .with(Widget::slider(
+            "Gravity x",
+            -1000.0..1000.0,
+            settings.gravity.x,
+            |edit: &mut PhysicsEdit, value| edit.settings.gravity.x = value,
+        ))
+        .with(Widget::slider(
+            "Gravity y",
+            -1000.0..2000.0,
+            settings.gravity.y,
+            |edit: &mut PhysicsEdit, value| edit.settings.gravity.y = value,
+        ))
+        .with(Widget::slider(
+            "Iterations",
+            1.0..30.0,
+            settings.iterations as f32,
+            |edit: &mut PhysicsEdit, value| edit.settings.iterations = value.round() as usize,
+        ))
+        .with(Widget::slider(
+            "Substeps",
+            1.0..8.0,
+            settings.substeps as f32,
+            |edit: &mut PhysicsEdit, value| edit.settings.substeps = value.round() as usize,
+        ))
+        .with(Widget::slider(
+            "Restitution",
+            0.0..1.0,
+            settings.restitution,
+            |edit: &mut PhysicsEdit, value| edit.settings.restitution = value,
+        ))
+        .with(Widget::slider(
+            "Friction",
+            0.0..1.0,
+            settings.friction,
+            |edit: &mut PhysicsEdit, value| edit.settings.friction = value,
+        ))
+        .with(Widget::slider(
+            "Damping",
+            0.0..5.0,
+            settings.damping,
+            |edit: &mut PhysicsEdit, value| edit.settings.damping = value,
+        ))
+        .with(Widget::slider(
+            "Air drag",
+            0.0..1.0,
+            settings.air_drag,
+            |edit: &mut PhysicsEdit, value| edit.settings.air_drag = value,
+        ))
+        .with(Widget::button(
+            "Save as default",
+            |edit: &mut PhysicsEdit| edit.save_default = true,
+        ))
+        .with(Widget::button(
+            "Save with craft",
+            |edit: &mut PhysicsEdit| edit.save_craft = true,
+        ))
+}
+
+/// Blends from grey at no strain towards blue in compression and red in tension
+fn strain_color(strain: f32) -> Color {
+    let t = (strain / MAX_STRAIN).clamp(-1.0, 1.0);
+    let (target, t) = if t < 0.0 {
+        (COMPRESSION_COLOR, -t)
+    } else {
+        (TENSION_COLOR, t)
+    };
+    Color::new(
+        GRAY.r + (target.r - GRAY.r) * t,
+        GRAY.g + (target.g - GRAY.g) * t,
+        GRAY.b + (target.b - GRAY.b) * t,
+        1.0,
+    )
+}
+
+/// Gradient bar explaining the stress colours, drawn in screen space
+fn draw_stress_legend() {
+    const WIDTH: f32 = 200.0;
+    const STEPS: usize = 40;
+    let (x, y) = (screen_width() - WIDTH - 20.0, 20.0);
+
+    draw_rectangle(x - 10.0, y - 10.0, WIDTH + 20.0, 60.0, palette().panel);
+    for i in 0..STEPS {
+        let t = i as f32 / (STEPS - 1) as f32;
+        let color = strain_color((t * 2.0 - 1.0) * MAX_STRAIN);
+        let step = WIDTH / STEPS as f32;
+        draw_rectangle(x + i as f32 * step, y, step + 1.0, 16.0, color);
+    }
+    draw_text("Compression", x, y + 36.0, 20.0, COMPRESSION_COLOR);
+    draw_text("Tension", x + WIDTH - 60.0, y + 36.0, 20.0, TENSION_COLOR);
+}
 
-        self.craft.nodes[b].pos -= correction;
+/// Draws each node's velocity as a line pointing where it is heading,
+/// `dt` is the time between a node's previous and current position
+fn draw_velocities(craft: &Craft, dt: f32) {
+    for node in &craft.nodes {
+        let velocity = (node.pos - node.prev_pos) / dt;
+        let end = node.pos + velocity * VELOCITY_SCALE;
+        draw_line(node.pos.x, node.pos.y, end.x, end.y, 1.5, PURPLE);
     }
 }
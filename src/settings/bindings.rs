@@ -0,0 +1,190 @@
+//! Vehicle Toolkit
+//!
+//! A binding is one way of triggering an action, either a key with modifiers, a mouse
+//! button, or a gamepad button or axis. An action can have any number of bindings.
+//!
+//! A binding with modifiers needs exactly those held, while a plain key ignores held modifiers
+//! so holding Shift to change the selection mode doesn't block panning. A plain key is only
+//! held back when a chord of the same key is bound and held, see `KeyBinds`, so `S` and
+//! Ctrl+S can still do different things. A modifier key bound on its own ignores itself.
+use std::fmt;
+
+use macroquad::input::{
+    KeyCode, MouseButton, is_key_down, is_key_pressed, is_key_released, is_mouse_button_down,
+    is_mouse_button_pressed, is_mouse_button_released,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gamepad::{self, GamepadAxis, GamepadButton},
+    keys::{Key, MouseButtonDef},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        ctrl: false,
+        shift: false,
+        alt: false,
+    };
+
+    /// Modifiers currently held down
+    pub fn held() -> Self {
+        let either = |a, b| is_key_down(a) || is_key_down(b);
+        Self {
+            ctrl: either(KeyCode::LeftControl, KeyCode::RightControl),
+            shift: either(KeyCode::LeftShift, KeyCode::RightShift),
+            alt: either(KeyCode::LeftAlt, KeyCode::RightAlt),
+        }
+    }
+
+    /// Modifiers held, not counting `key` itself if it is a modifier
+    fn held_besides(key: KeyCode) -> Self {
+        let mut held = Self::held();
+        match key {
+            KeyCode::LeftControl | KeyCode::RightControl => held.ctrl = false,
+            KeyCode::LeftShift | KeyCode::RightShift => held.shift = false,
+            KeyCode::LeftAlt | KeyCode::RightAlt => held.alt = false,
+            _ => (),
+        }
+        held
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+    /// Whether a binding needing these modifiers fires with `held` held
+    pub fn satisfied_by(&self, held: Modifiers) -> bool {
+        self.is_none() || *self == held
+    }
+}
+
+/// Whether the key is one of the modifier keys
+pub fn is_modifier(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::LeftControl
+            | KeyCode::RightControl
+            | KeyCode::LeftShift
+            | KeyCode::RightShift
+            | KeyCode::LeftAlt
+            | KeyCode::RightAlt
+    )
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key {
+        key: Key,
+        #[serde(default, skip_serializing_if = "Modifiers::is_none")]
+        mods: Modifiers,
+    },
+    Mouse(#[serde(with = "MouseButtonDef")] MouseButton),
+    GamepadButton(GamepadButton),
+    /// An axis pushed past the threshold, in the positive or negative direction
+    GamepadAxis {
+        axis: GamepadAxis,
+        positive: bool,
+    },
+}
+impl Binding {
+    /// A key with no modifiers
+    pub fn key(key: KeyCode) -> Self {
+        Self::Key {
+            key: Key(key),
+            mods: Modifiers::NONE,
+        }
+    }
+    pub fn chord(key: KeyCode, mods: Modifiers) -> Self {
+        Self::Key {
+            key: Key(key),
+            mods,
+        }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        match *self {
+            Binding::Key {
+                key: Key(key),
+                mods,
+            } => is_key_pressed(key) && mods.satisfied_by(Modifiers::held_besides(key)),
+            Binding::Mouse(button) => is_mouse_button_pressed(button),
+            Binding::GamepadButton(button) => gamepad::with_state(|current, previous| {
+                current.buttons.contains(&button) && !previous.buttons.contains(&button)
+            }),
+            Binding::GamepadAxis { axis, positive } => gamepad::with_state(|current, previous| {
+                current.axis_pushed(axis, positive) && !previous.axis_pushed(axis, positive)
+            }),
+        }
+    }
+    pub fn is_down(&self) -> bool {
+        match *self {
+            Binding::Key {
+                key: Key(key),
+                mods,
+            } => is_key_down(key) && mods.satisfied_by(Modifiers::held_besides(key)),
+            Binding::Mouse(button) => is_mouse_button_down(button),
+            Binding::GamepadButton(button) => {
+                gamepad::with_state(|current, _| current.buttons.contains(&button))
+            }
+            Binding::GamepadAxis { axis, positive } => {
+                gamepad::with_state(|current, _| current.axis_pushed(axis, positive))
+            }
+        }
+    }
+    /// Whether this is a key with modifiers, and that key and exactly those modifiers are held
+    pub fn is_chord_held(&self) -> bool {
+        match *self {
+            Binding::Key {
+                key: Key(key),
+                mods,
+            } => !mods.is_none() && is_key_down(key) && Modifiers::held_besides(key) == mods,
+            _ => false,
+        }
+    }
+    /// Released ignores modifiers, letting go of them first shouldn't swallow the release
+    pub fn is_released(&self) -> bool {
+        match *self {
+            Binding::Key { key: Key(key), .. } => is_key_released(key),
+            Binding::Mouse(button) => is_mouse_button_released(button),
+            Binding::GamepadButton(button) => gamepad::with_state(|current, previous| {
+                !current.buttons.contains(&button) && previous.buttons.contains(&button)
+            }),
+            Binding::GamepadAxis { axis, positive } => gamepad::with_state(|current, previous| {
+                !current.axis_pushed(axis, positive) && previous.axis_pushed(axis, positive)
+            }),
+        }
+    }
+}
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key {
+                key: Key(key),
+                mods,
+            } => {
+                if mods.ctrl {
+                    write!(f, "Ctrl+")?;
+                }
+                if mods.shift {
+                    write!(f, "Shift+")?;
+                }
+                if mods.alt {
+                    write!(f, "Alt+")?;
+                }
+                write!(f, "{key:?}")
+            }
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Binding::GamepadButton(button) => write!(f, "Pad {button:?}"),
+            Binding::GamepadAxis { axis, positive } => {
+                write!(f, "Pad {axis:?}{}", if *positive { "+" } else { "-" })
+            }
+        }
+    }
+}
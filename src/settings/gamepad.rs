@@ -0,0 +1,80 @@
+//! Vehicle Toolkit
+//!
+//! Gamepad buttons and axes that actions can be bound to.
+//!
+//! macroquad doesn't read gamepads itself, so the state here is fed in each frame with
+//! `update`, by the application from the gamepads found by `joystick`. Without a gamepad
+//! every button reads as up and every axis as centred.
+use std::{cell::RefCell, collections::HashMap, collections::HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// How far an axis has to be pushed to count as pressed
+pub const AXIS_THRESHOLD: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Buttons held and axis positions of every connected gamepad combined
+#[derive(Debug, Clone, Default)]
+pub struct GamepadState {
+    pub buttons: HashSet<GamepadButton>,
+    /// Each within `-1.0..=1.0`, missing axes are centred
+    pub axes: HashMap<GamepadAxis, f32>,
+}
+impl GamepadState {
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+    /// Whether the axis is pushed past the threshold in the given direction
+    pub fn axis_pushed(&self, axis: GamepadAxis, positive: bool) -> bool {
+        let value = self.axis(axis);
+        if positive {
+            value > AXIS_THRESHOLD
+        } else {
+            value < -AXIS_THRESHOLD
+        }
+    }
+}
+
+thread_local! {
+    /// This frame's and last frame's state, to tell when a button is first pressed
+    static STATE: RefCell<(GamepadState, GamepadState)> = RefCell::default();
+}
+
+/// Sets this frame's gamepad state, should be called once per frame before scenes update
+pub fn update(state: GamepadState) {
+    STATE.with_borrow_mut(|(current, previous)| {
+        *previous = std::mem::replace(current, state);
+    });
+}
+
+/// Calls `f` with this frame's and last frame's state
+pub(super) fn with_state<T>(f: impl FnOnce(&GamepadState, &GamepadState) -> T) -> T {
+    STATE.with_borrow(|(current, previous)| f(current, previous))
+}
@@ -0,0 +1,250 @@
+//! Vehicle Toolkit
+//!
+//! Gamepads read through the Linux joystick interface, `/dev/input/js*`.
+//!
+//! Each device sends small events whenever a button or axis changes, read without blocking
+//! once per frame. Buttons and axes are numbered the way the common Xbox style drivers number
+//! them, and the d-pad, which those drivers report as a pair of axes, is turned into buttons.
+//! Gamepads plugged in later are picked up by looking for new devices every few seconds.
+//! On other platforms no gamepads are found, so every button reads as up.
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, Read},
+    time::{Duration, Instant},
+};
+
+use super::gamepad::{AXIS_THRESHOLD, GamepadAxis, GamepadButton, GamepadState};
+
+/// Devices looked for, `/dev/input/js0` and so on
+const MAX_DEVICES: usize = 4;
+/// How often to look for newly connected gamepads
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/* Event layout, see linux/joystick.h */
+const EVENT_SIZE: usize = 8;
+const EVENT_BUTTON: u8 = 0x01;
+const EVENT_AXIS: u8 = 0x02;
+/// Set on the events sent when a device is opened, describing its starting state
+const EVENT_INIT: u8 = 0x80;
+
+/// Buttons in the order the driver numbers them
+const BUTTONS: [Option<GamepadButton>; 11] = [
+    Some(GamepadButton::South),
+    Some(GamepadButton::East),
+    Some(GamepadButton::West),
+    Some(GamepadButton::North),
+    Some(GamepadButton::LeftShoulder),
+    Some(GamepadButton::RightShoulder),
+    Some(GamepadButton::Select),
+    Some(GamepadButton::Start),
+    // The guide button is kept by the system
+    None,
+    Some(GamepadButton::LeftStick),
+    Some(GamepadButton::RightStick),
+];
+
+/// Axes in the order the driver numbers them
+enum Axis {
+    Stick(GamepadAxis),
+    /// Reversed so pushing the stick up is positive
+    StickY(GamepadAxis),
+    /// Rests at the bottom of its range, mapped to `0.0..=1.0`
+    Trigger(GamepadAxis),
+    DPadX,
+    DPadY,
+}
+const AXES: [Axis; 8] = [
+    Axis::Stick(GamepadAxis::LeftStickX),
+    Axis::StickY(GamepadAxis::LeftStickY),
+    Axis::Trigger(GamepadAxis::LeftTrigger),
+    Axis::Stick(GamepadAxis::RightStickX),
+    Axis::StickY(GamepadAxis::RightStickY),
+    Axis::Trigger(GamepadAxis::RightTrigger),
+    Axis::DPadX,
+    Axis::DPadY,
+];
+
+struct Device {
+    index: usize,
+    file: File,
+    state: GamepadState,
+}
+
+/// Every connected gamepad
+pub struct Joysticks {
+    devices: Vec<Device>,
+    last_scan: Option<Instant>,
+}
+impl Default for Joysticks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Joysticks {
+    pub fn new() -> Self {
+        Self {
+            devices: vec![],
+            last_scan: None,
+        }
+    }
+
+    /// Reads what changed since the last poll, returning the combined state of every gamepad
+    pub fn poll(&mut self) -> GamepadState {
+        if self
+            .last_scan
+            .is_none_or(|scan| scan.elapsed() >= SCAN_INTERVAL)
+        {
+            self.last_scan = Some(Instant::now());
+            self.scan();
+        }
+        // A device which fails to read has been unplugged
+        self.devices
+            .retain_mut(|device| read_events(device).is_ok());
+
+        let mut combined = GamepadState::default();
+        for device in &self.devices {
+            combined.buttons.extend(&device.state.buttons);
+            for (&axis, &value) in &device.state.axes {
+                let current = combined.axes.entry(axis).or_default();
+                if value.abs() > current.abs() {
+                    *current = value;
+                }
+            }
+        }
+        combined
+    }
+
+    /// Opens any device which isn't open yet
+    fn scan(&mut self) {
+        let open: HashSet<usize> = self.devices.iter().map(|device| device.index).collect();
+        for index in (0..MAX_DEVICES).filter(|index| !open.contains(index)) {
+            if let Some(file) = open_device(index) {
+                self.devices.push(Device {
+                    index,
+                    file,
+                    state: GamepadState::default(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_device(index: usize) -> Option<File> {
+    use std::{fs::OpenOptions, os::unix::fs::OpenOptionsExt};
+    const O_NONBLOCK: i32 = 0o4000;
+
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(O_NONBLOCK)
+        .open(format!("/dev/input/js{index}"))
+        .ok()
+}
+#[cfg(not(target_os = "linux"))]
+fn open_device(_index: usize) -> Option<File> {
+    None
+}
+
+/// Applies every waiting event to the device's state
+fn read_events(device: &mut Device) -> io::Result<()> {
+    let mut event = [0; EVENT_SIZE];
+    loop {
+        match device.file.read(&mut event) {
+            Ok(EVENT_SIZE) => apply_event(&mut device.state, event),
+            Ok(_) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Updates a gamepad's state from one event, laid out as time, value, type and number
+fn apply_event(state: &mut GamepadState, event: [u8; EVENT_SIZE]) {
+    let value = i16::from_ne_bytes([event[4], event[5]]);
+    let number = event[7] as usize;
+    match event[6] & !EVENT_INIT {
+        EVENT_BUTTON => {
+            if let Some(Some(button)) = BUTTONS.get(number) {
+                set_button(state, *button, value != 0);
+            }
+        }
+        EVENT_AXIS => {
+            let value = value as f32 / i16::MAX as f32;
+            match AXES.get(number) {
+                Some(Axis::Stick(axis)) => {
+                    state.axes.insert(*axis, value.clamp(-1.0, 1.0));
+                }
+                Some(Axis::StickY(axis)) => {
+                    state.axes.insert(*axis, (-value).clamp(-1.0, 1.0));
+                }
+                Some(Axis::Trigger(axis)) => {
+                    state
+                        .axes
+                        .insert(*axis, ((value + 1.0) / 2.0).clamp(0.0, 1.0));
+                }
+                Some(Axis::DPadX) => {
+                    set_button(state, GamepadButton::DPadLeft, value < -AXIS_THRESHOLD);
+                    set_button(state, GamepadButton::DPadRight, value > AXIS_THRESHOLD);
+                }
+                Some(Axis::DPadY) => {
+                    set_button(state, GamepadButton::DPadUp, value < -AXIS_THRESHOLD);
+                    set_button(state, GamepadButton::DPadDown, value > AXIS_THRESHOLD);
+                }
+                None => (),
+            }
+        }
+        _ => (),
+    }
+}
+
+fn set_button(state: &mut GamepadState, button: GamepadButton, down: bool) {
+    if down {
+        state.buttons.insert(button);
+    } else {
+        state.buttons.remove(&button);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: u8, number: u8, value: i16) -> [u8; EVENT_SIZE] {
+        let [low, high] = value.to_ne_bytes();
+        [0, 0, 0, 0, low, high, kind, number]
+    }
+
+    #[test]
+    fn buttons_follow_their_events() {
+        let mut state = GamepadState::default();
+        apply_event(&mut state, event(EVENT_BUTTON | EVENT_INIT, 0, 1));
+        apply_event(&mut state, event(EVENT_BUTTON, 7, 1));
+        assert!(state.buttons.contains(&GamepadButton::South));
+        assert!(state.buttons.contains(&GamepadButton::Start));
+
+        apply_event(&mut state, event(EVENT_BUTTON, 0, 0));
+        assert!(!state.buttons.contains(&GamepadButton::South));
+    }
+
+    #[test]
+    fn axes_are_scaled_and_sticks_point_up() {
+        let mut state = GamepadState::default();
+        apply_event(&mut state, event(EVENT_AXIS, 0, i16::MAX));
+        apply_event(&mut state, event(EVENT_AXIS, 1, -i16::MAX));
+        apply_event(&mut state, event(EVENT_AXIS, 2, -i16::MAX));
+        assert_eq!(state.axis(GamepadAxis::LeftStickX), 1.0);
+        assert_eq!(state.axis(GamepadAxis::LeftStickY), 1.0);
+        assert_eq!(state.axis(GamepadAxis::LeftTrigger), 0.0);
+    }
+
+    #[test]
+    fn the_dpad_presses_buttons() {
+        let mut state = GamepadState::default();
+        apply_event(&mut state, event(EVENT_AXIS, 7, -i16::MAX));
+        assert!(state.buttons.contains(&GamepadButton::DPadUp));
+        apply_event(&mut state, event(EVENT_AXIS, 7, 0));
+        assert!(state.buttons.is_empty());
+    }
+}
@@ -0,0 +1,755 @@
+//! Vehicle Toolkit
+//!
+//! User settings, saved to `data/settings.json`.
+//!
+//! Every action belongs to an input context, and only fires while its context is active or
+//! if it is global. Workspace actions fire in both the editor and the simulation. The same
+//! binding can be used by actions in different contexts, so a key can mean one thing in the
+//! editor and another in the simulation. The settings file groups the bindings by context.
+//!
+//! Key bindings map each action to a list of bindings, any of which triggers it. Actions
+//! missing from the file keep their default bindings, so new actions show up without the file
+//! having to be rewritten, and a file which can't be read falls back to the defaults entirely
+//! rather than stopping the app.
+use macroquad::prelude::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io,
+    path::PathBuf,
+};
+
+mod bindings;
+pub mod gamepad;
+pub mod joystick;
+mod keys;
+pub use bindings::{Binding, Modifiers, is_modifier};
+pub use keys::Key;
+
+use gamepad::{GamepadAxis, GamepadButton};
+
+/// Where settings are kept between runs
+pub const SETTINGS_PATH: &str = "data/settings.json";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub key_binds: KeyBinds,
+    /// Name of the theme in use, the built-in theme if `None`
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+impl Settings {
+    /// Loads settings from a JSON file
+    pub fn load(file_path: PathBuf) -> io::Result<Settings> {
+        let file = File::open(file_path)?;
+        serde_json::from_reader(file).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "JSON settings format seems to be invalid",
+            )
+        })
+    }
+
+    /// Saves settings into a JSON file
+    pub fn save(&self, file_path: PathBuf) -> io::Result<()> {
+        let file = File::create(file_path)?;
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|_| io::Error::other("Error saving settings"))
+    }
+
+    /// Loads the saved settings, falling back to the defaults if they can't be read,
+    /// along with a message for the user if something went wrong.
+    /// A missing or empty file is filled in with the defaults, an invalid one is left alone
+    /// so it can be fixed by hand. Conflicting bindings are shown on the key bindings screen.
+    pub fn load_or_default() -> (Settings, Option<String>) {
+        let path = PathBuf::from(SETTINGS_PATH);
+        let unwritten = fs::read_to_string(&path).map_or(true, |text| text.trim().is_empty());
+        if unwritten {
+            let settings = Settings::default();
+            let error = settings
+                .save(path)
+                .err()
+                .map(|err| format!("Couldn't save default settings: {err}"));
+            return (settings, error);
+        }
+
+        match Settings::load(path) {
+            Ok(settings) => (settings, None),
+            Err(err) => (
+                Settings::default(),
+                Some(format!("Couldn't load settings, using defaults: {err}")),
+            ),
+        }
+    }
+}
+
+/// Where an action can be used, each scene picks the context it takes input in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InputContext {
+    /// Active alongside every other context
+    Global,
+    /// Active in both the editor and the simulation
+    Workspace,
+    Menu,
+    Editor,
+    Simulation,
+}
+impl InputContext {
+    pub const ALL: [InputContext; 5] = [
+        InputContext::Global,
+        InputContext::Workspace,
+        InputContext::Menu,
+        InputContext::Editor,
+        InputContext::Simulation,
+    ];
+    /// Contexts a scene can take input in, the others are shared between them
+    const SCENES: [InputContext; 3] = [
+        InputContext::Menu,
+        InputContext::Editor,
+        InputContext::Simulation,
+    ];
+
+    /// Whether actions in this context respond while a scene takes input in `scene`
+    pub fn is_active_in(self, scene: InputContext) -> bool {
+        match self {
+            InputContext::Global => true,
+            InputContext::Workspace => {
+                matches!(scene, InputContext::Editor | InputContext::Simulation)
+            }
+            _ => self == scene,
+        }
+    }
+    /// Whether actions in the two contexts can be active at the same time
+    pub fn overlaps(self, other: InputContext) -> bool {
+        self == other
+            || Self::SCENES
+                .into_iter()
+                .any(|scene| self.is_active_in(scene) && other.is_active_in(scene))
+    }
+}
+
+/// Declares `Action` along with `Action::ALL`, so every action is always listed
+macro_rules! actions {
+    ($($(#[$meta:meta])* $action:ident,)*) => {
+        #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Action {
+            $($(#[$meta])* $action,)*
+        }
+        impl Action {
+            /// Every action, in the order they are listed to the user
+            pub const ALL: [Action; { [$(Action::$action,)*].len() }] = [$(Action::$action,)*];
+        }
+    };
+}
+
+actions! {
+    /* Workspace Controls */
+    SwitchScene,
+
+    /* Global Controls */
+    MoveCamUp,
+    MoveCameDown,
+    MoveCamLeft,
+    MoveCamRight,
+
+    ZoomIn,
+    ZoomOut,
+    CycleTheme,
+    /// Asks to quit, or goes back in scenes which use it that way
+    #[serde(alias = "OpenPauseMenu")]
+    Quit,
+
+    /* Menu Controls */
+    OpenSettings,
+    MenuUp,
+    MenuDown,
+    MenuSelect,
+    MenuBack,
+
+    /* Editor Controls */
+    ClearPoints,
+    NewCraft,
+    SaveCraft,
+    SaveCraftAs,
+
+    PlaceNodes,
+    PlaceRods,
+    PlaceTriangles,
+    PlaceHinge,
+    PlaceMotor,
+    CycleRodType,
+    ToggleWinch,
+
+    Delete,
+
+    ToggleRigidity,
+    AddBracing,
+
+    ToggleLasso,
+    ToggleSelectMode,
+    SelectSubtract,
+    SelectToggle,
+
+    GroupSelection,
+
+    /* Simulation Controls */
+    SpawnCraft,
+    ToggleHitch,
+    ToggleCameraFollow,
+    TogglePause,
+    StepFrame,
+    SlowDown,
+    SpeedUp,
+    ResetSimulation,
+    SaveCheckpoint,
+    LoadCheckpoint,
+    SetSpawn,
+    RotateSpawnLeft,
+    RotateSpawnRight,
+    ToggleStress,
+    ToggleVelocities,
+    ToggleWind,
+    TogglePhysicsPanel,
+
+    /* Craft Controls */
+    Channel1Reverse,
+    Channel1Forward,
+    Channel2Reverse,
+    Channel2Forward,
+    Channel3Reverse,
+    Channel3Forward,
+}
+
+impl Action {
+    /// Context the action is used in, every action is listed so a new one has to be given one
+    pub fn context(&self) -> InputContext {
+        match self {
+            Action::MoveCamUp
+            | Action::MoveCameDown
+            | Action::MoveCamLeft
+            | Action::MoveCamRight
+            | Action::ZoomIn
+            | Action::ZoomOut
+            | Action::CycleTheme
+            | Action::Quit => InputContext::Global,
+            Action::SwitchScene => InputContext::Workspace,
+            Action::OpenSettings
+            | Action::MenuUp
+            | Action::MenuDown
+            | Action::MenuSelect
+            | Action::MenuBack => InputContext::Menu,
+            Action::ClearPoints
+            | Action::NewCraft
+            | Action::SaveCraft
+            | Action::SaveCraftAs
+            | Action::PlaceNodes
+            | Action::PlaceRods
+            | Action::PlaceTriangles
+            | Action::PlaceHinge
+            | Action::PlaceMotor
+            | Action::CycleRodType
+            | Action::ToggleWinch
+            | Action::Delete
+            | Action::ToggleRigidity
+            | Action::AddBracing
+            | Action::ToggleLasso
+            | Action::ToggleSelectMode
+            | Action::SelectSubtract
+            | Action::SelectToggle
+            | Action::GroupSelection => InputContext::Editor,
+            Action::SpawnCraft
+            | Action::ToggleHitch
+            | Action::ToggleCameraFollow
+            | Action::TogglePause
+            | Action::StepFrame
+            | Action::SlowDown
+            | Action::SpeedUp
+            | Action::ResetSimulation
+            | Action::SaveCheckpoint
+            | Action::LoadCheckpoint
+            | Action::SetSpawn
+            | Action::RotateSpawnLeft
+            | Action::RotateSpawnRight
+            | Action::ToggleStress
+            | Action::ToggleVelocities
+            | Action::ToggleWind
+            | Action::TogglePhysicsPanel
+            | Action::Channel1Reverse
+            | Action::Channel1Forward
+            | Action::Channel2Reverse
+            | Action::Channel2Forward
+            | Action::Channel3Reverse
+            | Action::Channel3Forward => InputContext::Simulation,
+        }
+    }
+
+    /// Name of the action split into words, `ToggleWind` becomes `Toggle Wind`
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for c in format!("{self:?}").chars() {
+            if c.is_uppercase() && !name.is_empty() {
+                name.push(' ');
+            }
+            name.push(c);
+        }
+        name
+    }
+}
+
+/// Bindings of an action as saved, older files have a single key per action
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedBindings {
+    Key(Key),
+    List(Vec<Binding>),
+}
+
+/// Key bindings as saved, older files don't group actions by context
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedKeyBinds {
+    Grouped(BTreeMap<InputContext, BTreeMap<Action, SavedBindings>>),
+    Flat(BTreeMap<Action, SavedBindings>),
+}
+
+type GroupedBindings = BTreeMap<InputContext, BTreeMap<Action, Vec<Binding>>>;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "SavedKeyBinds", into = "GroupedBindings")]
+pub struct KeyBinds {
+    action_to_bindings: HashMap<Action, Vec<Binding>>,
+    /// Context of the scene taking input
+    context: InputContext,
+}
+impl From<SavedKeyBinds> for KeyBinds {
+    fn from(saved: SavedKeyBinds) -> Self {
+        let actions = match saved {
+            SavedKeyBinds::Grouped(contexts) => contexts.into_values().flatten().collect(),
+            SavedKeyBinds::Flat(actions) => actions,
+        };
+        let mut key_binds = KeyBinds::default();
+        for (action, bindings) in actions {
+            let bindings = match bindings {
+                SavedBindings::Key(Key(key)) => vec![Binding::key(key)],
+                SavedBindings::List(bindings) => bindings,
+            };
+            key_binds.action_to_bindings.insert(action, bindings);
+        }
+        key_binds
+    }
+}
+impl From<KeyBinds> for GroupedBindings {
+    fn from(key_binds: KeyBinds) -> Self {
+        let mut grouped = GroupedBindings::new();
+        for (action, bindings) in key_binds.action_to_bindings {
+            grouped
+                .entry(action.context())
+                .or_default()
+                .insert(action, bindings);
+        }
+        grouped
+    }
+}
+impl KeyBinds {
+    /// Switches which context's actions respond to input, global actions always do
+    pub fn set_context(&mut self, context: InputContext) {
+        self.context = context;
+    }
+    fn is_active(&self, action: Action) -> bool {
+        action.context().is_active_in(self.context)
+    }
+
+    /* Remapping */
+    /// Replaces every binding of the action with a single one
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        self.action_to_bindings.insert(action, vec![binding]);
+    }
+    /// Adds another way of triggering the action
+    pub fn add_binding(&mut self, action: Action, binding: Binding) {
+        let bindings = self.action_to_bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+    pub fn unbind(&mut self, action: Action) {
+        self.action_to_bindings.remove(&action);
+    }
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.action_to_bindings
+            .get(&action)
+            .map_or(&[], |bindings| bindings)
+    }
+    /// Bindings shared by actions which can be active at the same time, with those actions
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<Action>)> {
+        let mut by_binding: HashMap<Binding, Vec<Action>> = HashMap::new();
+        for (&action, bindings) in &self.action_to_bindings {
+            for &binding in bindings {
+                by_binding.entry(binding).or_default().push(action);
+            }
+        }
+        let mut conflicts: Vec<_> = by_binding
+            .into_iter()
+            .map(|(binding, actions)| {
+                let mut clashing: Vec<Action> = actions
+                    .iter()
+                    .copied()
+                    .filter(|a| {
+                        actions
+                            .iter()
+                            .any(|b| a != b && a.context().overlaps(b.context()))
+                    })
+                    .collect();
+                clashing.sort();
+                (binding, clashing)
+            })
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect();
+        conflicts.sort_by_key(|(_, actions)| actions[0]);
+        conflicts
+    }
+
+    /* Input */
+    pub fn is_key_pressed(&self, action: Action) -> bool {
+        self.is_active(action)
+            && self
+                .bindings(action)
+                .iter()
+                .any(|binding| binding.is_pressed() && !self.is_shadowed(binding))
+    }
+    pub fn is_key_down(&self, action: Action) -> bool {
+        self.is_active(action)
+            && self
+                .bindings(action)
+                .iter()
+                .any(|binding| binding.is_down() && !self.is_shadowed(binding))
+    }
+    /// Whether a plain key is held back because an active chord of the same key is held
+    fn is_shadowed(&self, binding: &Binding) -> bool {
+        let Binding::Key { key, mods } = binding else {
+            return false;
+        };
+        mods.is_none()
+            && self
+                .action_to_bindings
+                .iter()
+                .filter(|&(&action, _)| self.is_active(action))
+                .flat_map(|(_, bindings)| bindings)
+                .any(|other| {
+                    matches!(other, Binding::Key { key: other_key, .. } if other_key == key)
+                        && other.is_chord_held()
+                })
+    }
+    pub fn is_key_released(&self, action: Action) -> bool {
+        self.is_active(action) && self.bindings(action).iter().any(Binding::is_released)
+    }
+}
+impl Default for KeyBinds {
+    fn default() -> Self {
+        let mut map = HashMap::new();
+        map.insert(Action::ClearPoints, vec![Binding::key(KeyCode::C)]);
+
+        map.insert(Action::PlaceNodes, vec![Binding::key(KeyCode::N)]);
+        map.insert(Action::PlaceRods, vec![Binding::key(KeyCode::R)]);
+        map.insert(Action::PlaceTriangles, vec![Binding::key(KeyCode::T)]);
+        map.insert(Action::PlaceHinge, vec![Binding::key(KeyCode::K)]);
+        map.insert(Action::PlaceMotor, vec![Binding::key(KeyCode::U)]);
+        map.insert(Action::CycleRodType, vec![Binding::key(KeyCode::Tab)]);
+        map.insert(Action::ToggleWinch, vec![Binding::key(KeyCode::W)]);
+
+        map.insert(Action::ZoomIn, vec![Binding::key(KeyCode::Z)]);
+        map.insert(Action::ZoomOut, vec![Binding::key(KeyCode::X)]);
+        map.insert(Action::CycleTheme, vec![Binding::key(KeyCode::F3)]);
+        map.insert(Action::Quit, vec![Binding::key(KeyCode::Escape)]);
+
+        map.insert(Action::Delete, vec![Binding::key(KeyCode::Backspace)]);
+
+        map.insert(Action::ToggleRigidity, vec![Binding::key(KeyCode::M)]);
+        map.insert(Action::AddBracing, vec![Binding::key(KeyCode::B)]);
+
+        map.insert(Action::ToggleLasso, vec![Binding::key(KeyCode::L)]);
+        map.insert(Action::ToggleSelectMode, vec![Binding::key(KeyCode::I)]);
+        map.insert(
+            Action::SelectSubtract,
+            vec![Binding::key(KeyCode::LeftControl)],
+        );
+        map.insert(Action::SelectToggle, vec![Binding::key(KeyCode::LeftShift)]);
+
+        map.insert(Action::GroupSelection, vec![Binding::key(KeyCode::G)]);
+
+        map.insert(Action::SwitchScene, vec![Binding::key(KeyCode::Space)]);
+
+        map.insert(Action::NewCraft, vec![Binding::key(KeyCode::S)]);
+        map.insert(
+            Action::SaveCraft,
+            vec![Binding::chord(
+                KeyCode::S,
+                Modifiers {
+                    ctrl: true,
+                    ..Modifiers::NONE
+                },
+            )],
+        );
+        map.insert(
+            Action::SaveCraftAs,
+            vec![Binding::chord(
+                KeyCode::S,
+                Modifiers {
+                    ctrl: true,
+                    shift: true,
+                    ..Modifiers::NONE
+                },
+            )],
+        );
+
+        map.insert(Action::OpenSettings, vec![Binding::key(KeyCode::F1)]);
+        map.insert(Action::MenuUp, vec![Binding::key(KeyCode::W)]);
+        map.insert(Action::MenuDown, vec![Binding::key(KeyCode::S)]);
+        map.insert(Action::MenuSelect, vec![Binding::key(KeyCode::Enter)]);
+        map.insert(Action::MenuBack, vec![Binding::key(KeyCode::Backspace)]);
+
+        map.insert(Action::SpawnCraft, vec![Binding::key(KeyCode::P)]);
+        map.insert(Action::ToggleHitch, vec![Binding::key(KeyCode::H)]);
+        map.insert(Action::ToggleCameraFollow, vec![Binding::key(KeyCode::F)]);
+        map.insert(Action::TogglePause, vec![Binding::key(KeyCode::Enter)]);
+        map.insert(Action::StepFrame, vec![Binding::key(KeyCode::Period)]);
+        map.insert(Action::SlowDown, vec![Binding::key(KeyCode::Minus)]);
+        map.insert(Action::SpeedUp, vec![Binding::key(KeyCode::Equal)]);
+        map.insert(Action::ResetSimulation, vec![Binding::key(KeyCode::Home)]);
+        map.insert(Action::SaveCheckpoint, vec![Binding::key(KeyCode::F5)]);
+        map.insert(Action::LoadCheckpoint, vec![Binding::key(KeyCode::F9)]);
+        map.insert(Action::SetSpawn, vec![Binding::key(KeyCode::O)]);
+        map.insert(Action::RotateSpawnLeft, vec![Binding::key(KeyCode::Q)]);
+        map.insert(Action::RotateSpawnRight, vec![Binding::key(KeyCode::E)]);
+        map.insert(Action::ToggleStress, vec![Binding::key(KeyCode::Y)]);
+        map.insert(Action::ToggleVelocities, vec![Binding::key(KeyCode::V)]);
+        map.insert(Action::ToggleWind, vec![Binding::key(KeyCode::J)]);
+        map.insert(Action::TogglePhysicsPanel, vec![Binding::key(KeyCode::F2)]);
+
+        map.insert(Action::Channel1Reverse, vec![Binding::key(KeyCode::Key1)]);
+        map.insert(Action::Channel1Forward, vec![Binding::key(KeyCode::Key2)]);
+        map.insert(Action::Channel2Reverse, vec![Binding::key(KeyCode::Key3)]);
+        map.insert(Action::Channel2Forward, vec![Binding::key(KeyCode::Key4)]);
+        map.insert(Action::Channel3Reverse, vec![Binding::key(KeyCode::Key5)]);
+        map.insert(Action::Channel3Forward, vec![Binding::key(KeyCode::Key6)]);
+
+        map.insert(Action::MoveCamUp, vec![Binding::key(KeyCode::Up)]);
+        map.insert(Action::MoveCameDown, vec![Binding::key(KeyCode::Down)]);
+        map.insert(Action::MoveCamLeft, vec![Binding::key(KeyCode::Left)]);
+        map.insert(Action::MoveCamRight, vec![Binding::key(KeyCode::Right)]);
+
+        // Gamepad defaults, the left stick and right stick drive the control channels
+        let axis = |axis, positive| Binding::GamepadAxis { axis, positive };
+        let pad = [
+            (
+                Action::Channel1Reverse,
+                axis(GamepadAxis::LeftStickX, false),
+            ),
+            (Action::Channel1Forward, axis(GamepadAxis::LeftStickX, true)),
+            (
+                Action::Channel2Reverse,
+                axis(GamepadAxis::LeftStickY, false),
+            ),
+            (Action::Channel2Forward, axis(GamepadAxis::LeftStickY, true)),
+            (
+                Action::Channel3Reverse,
+                axis(GamepadAxis::RightStickX, false),
+            ),
+            (
+                Action::Channel3Forward,
+                axis(GamepadAxis::RightStickX, true),
+            ),
+            (
+                Action::TogglePause,
+                Binding::GamepadButton(GamepadButton::Start),
+            ),
+            (
+                Action::MenuUp,
+                Binding::GamepadButton(GamepadButton::DPadUp),
+            ),
+            (
+                Action::MenuDown,
+                Binding::GamepadButton(GamepadButton::DPadDown),
+            ),
+            (
+                Action::MenuSelect,
+                Binding::GamepadButton(GamepadButton::South),
+            ),
+            (
+                Action::MenuBack,
+                Binding::GamepadButton(GamepadButton::East),
+            ),
+        ];
+        for (action, binding) in pad {
+            map.entry(action).or_insert_with(Vec::new).push(binding);
+        }
+
+        Self {
+            action_to_bindings: map,
+            context: InputContext::Global,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::MouseButton;
+
+    fn parse(json: &str) -> KeyBinds {
+        serde_json::from_str(json).expect("key binds should parse")
+    }
+
+    #[test]
+    fn legacy_single_keys_are_read_as_lists() {
+        let key_binds = parse(r#"{ "PlaceNodes": "A", "Delete": "X" }"#);
+        assert_eq!(
+            key_binds.bindings(Action::PlaceNodes),
+            [Binding::key(KeyCode::A)]
+        );
+        assert_eq!(
+            key_binds.bindings(Action::Delete),
+            [Binding::key(KeyCode::X)]
+        );
+    }
+
+    #[test]
+    fn grouped_bindings_survive_a_round_trip() {
+        let mut key_binds = KeyBinds::default();
+        key_binds.bind(Action::PlaceNodes, Binding::key(KeyCode::A));
+        key_binds.add_binding(Action::PlaceNodes, Binding::Mouse(MouseButton::Right));
+
+        let json = serde_json::to_string(&key_binds).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(saved["Editor"]["PlaceNodes"].is_array());
+
+        let loaded = parse(&json);
+        for action in Action::ALL {
+            assert_eq!(loaded.bindings(action), key_binds.bindings(action));
+        }
+    }
+
+    #[test]
+    fn missing_actions_keep_their_defaults() {
+        let key_binds = parse(r#"{ "Editor": { "PlaceNodes": "A" } }"#);
+        assert_eq!(
+            key_binds.bindings(Action::PlaceRods),
+            KeyBinds::default().bindings(Action::PlaceRods)
+        );
+    }
+
+    #[test]
+    fn every_action_is_listed_once() {
+        let mut actions = Action::ALL.to_vec();
+        actions.sort();
+        actions.dedup();
+        assert_eq!(actions.len(), Action::ALL.len());
+    }
+
+    #[test]
+    fn menus_can_be_used_with_a_gamepad() {
+        let key_binds = KeyBinds::default();
+        for action in [
+            Action::MenuUp,
+            Action::MenuDown,
+            Action::MenuSelect,
+            Action::MenuBack,
+        ] {
+            let bindings = key_binds.bindings(action);
+            assert!(
+                bindings.iter().any(|binding| matches!(
+                    binding,
+                    Binding::GamepadButton(_) | Binding::GamepadAxis { .. }
+                )),
+                "{action:?} has no gamepad binding"
+            );
+        }
+    }
+
+    #[test]
+    fn actions_belong_to_their_context() {
+        assert_eq!(Action::CycleTheme.context(), InputContext::Global);
+        assert_eq!(Action::MenuBack.context(), InputContext::Menu);
+        assert_eq!(Action::SaveCraft.context(), InputContext::Editor);
+        assert_eq!(Action::Channel3Forward.context(), InputContext::Simulation);
+    }
+
+    #[test]
+    fn only_the_active_context_and_global_actions_respond() {
+        let mut key_binds = KeyBinds::default();
+        key_binds.set_context(InputContext::Editor);
+        assert!(key_binds.is_active(Action::PlaceNodes));
+        assert!(key_binds.is_active(Action::ZoomIn));
+        assert!(!key_binds.is_active(Action::SpawnCraft));
+        assert!(!key_binds.is_active(Action::MenuSelect));
+    }
+
+    #[test]
+    fn switching_scenes_only_works_in_the_workspace() {
+        assert_eq!(Action::SwitchScene.context(), InputContext::Workspace);
+        let mut key_binds = KeyBinds::default();
+        for (context, active) in [
+            (InputContext::Editor, true),
+            (InputContext::Simulation, true),
+            (InputContext::Menu, false),
+        ] {
+            key_binds.set_context(context);
+            assert_eq!(key_binds.is_active(Action::SwitchScene), active);
+        }
+        assert!(InputContext::Workspace.overlaps(InputContext::Simulation));
+        assert!(!InputContext::Workspace.overlaps(InputContext::Menu));
+    }
+
+    #[test]
+    fn saved_files_group_actions_by_context() {
+        let json = serde_json::to_value(KeyBinds::default()).unwrap();
+        for action in Action::ALL {
+            let context = format!("{:?}", action.context());
+            let name = format!("{action:?}");
+            assert!(
+                json[&context].get(&name).is_some(),
+                "{name} not under {context}"
+            );
+        }
+    }
+
+    #[test]
+    fn plain_keys_ignore_held_modifiers() {
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+        let ctrl_shift = Modifiers {
+            ctrl: true,
+            ..shift
+        };
+        assert!(Modifiers::NONE.satisfied_by(Modifiers::NONE));
+        assert!(Modifiers::NONE.satisfied_by(ctrl_shift));
+        assert!(shift.satisfied_by(shift));
+        assert!(!shift.satisfied_by(Modifiers::NONE));
+        assert!(!shift.satisfied_by(ctrl_shift));
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert!(KeyBinds::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn only_overlapping_contexts_conflict() {
+        let mut key_binds = KeyBinds::default();
+        // Editor and simulation are never active together
+        key_binds.bind(Action::PlaceNodes, Binding::key(KeyCode::P));
+        assert!(key_binds.conflicts().is_empty());
+
+        key_binds.bind(Action::ZoomIn, Binding::key(KeyCode::P));
+        let conflicts = key_binds.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0, Binding::key(KeyCode::P));
+        assert_eq!(
+            conflicts[0].1,
+            [Action::ZoomIn, Action::PlaceNodes, Action::SpawnCraft]
+        );
+    }
+}
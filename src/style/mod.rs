@@ -0,0 +1,118 @@
+//! Vehicle Toolkit
+//!
+//! Styling of the app, the ui skin and the colours scenes draw with, built from a theme.
+//!
+//! The default theme's assets are built into the app, so an image or font a theme names
+//! which is missing or can't be read is replaced by the default one rather than stopping
+//! the app.
+use macroquad::{
+    prelude::{Image, RectOffset, load_file, load_image, load_ttf_font_from_bytes},
+    ui::{Skin, root_ui},
+};
+
+mod theme;
+pub use theme::{Theme, palette, theme_names};
+
+/* Built-in assets */
+const WINDOW_BACKGROUND: &[u8] = include_bytes!("../../assets/ui/window_bg.png");
+const BUTTON_BACKGROUND: &[u8] = include_bytes!("../../assets/ui/button_bg.png");
+const BUTTON_CLICKED_BACKGROUND: &[u8] = include_bytes!("../../assets/ui/button_clicked_bg.png");
+const BUTTON_HOVERED_BACKGROUND: &[u8] = include_bytes!("../../assets/ui/button_hovered_bg.png");
+const FONT: &[u8] = include_bytes!("../../assets/ui/fonts/ShareTech-Regular.ttf");
+
+/// Function that defines UI styling, noting each asset replaced by the built-in one in `fallbacks`
+pub async fn build_application_style(theme: &Theme, fallbacks: &mut Vec<String>) -> Skin {
+    let window_background =
+        load_image_or(&theme.window_background, WINDOW_BACKGROUND, fallbacks).await;
+    let button_background =
+        load_image_or(&theme.button_background, BUTTON_BACKGROUND, fallbacks).await;
+    let button_clicked_background = load_image_or(
+        &theme.button_clicked_background,
+        BUTTON_CLICKED_BACKGROUND,
+        fallbacks,
+    )
+    .await;
+    let button_hovered_background = load_image_or(
+        &theme.button_hovered_background,
+        BUTTON_HOVERED_BACKGROUND,
+        fallbacks,
+    )
+    .await;
+
+    let font = load_font_or(&theme.font, FONT, fallbacks).await;
+
+    let window_style = root_ui()
+        .style_builder()
+        .background_margin(RectOffset::new(32.0, 76.0, 44.0, 20.0))
+        .margin(RectOffset::new(0.0, 0.0, 0.0, 0.0))
+        .background(window_background)
+        .font(&font)
+        .expect("font was checked when loaded")
+        .build();
+    let button_style = root_ui()
+        .style_builder()
+        .background_margin(RectOffset::new(16.0, 16.0, 16.0, 16.0))
+        .margin(RectOffset::new(16.0, 0.0, -8.0, -8.0))
+        .background(button_background)
+        .background_clicked(button_clicked_background)
+        .background_hovered(button_hovered_background)
+        .text_color(theme.text_color)
+        .font(&font)
+        .expect("font was checked when loaded")
+        .font_size(theme.font_size)
+        .build();
+    let label_style = root_ui()
+        .style_builder()
+        .text_color(theme.text_color)
+        .font(&font)
+        .expect("font was checked when loaded")
+        .font_size(theme.font_size)
+        .build();
+    Skin {
+        window_style,
+        button_style,
+        label_style,
+        ..root_ui().default_skin()
+    }
+}
+
+/// Switches to the theme called `name`, replacing the ui skin and the palette scenes draw with.
+/// Returns a message listing whatever couldn't be loaded and was replaced by the built-in one
+pub async fn apply_theme(name: Option<&str>) -> Option<String> {
+    let (theme, error) = Theme::named(name);
+    let mut fallbacks: Vec<String> = error.into_iter().collect();
+    let skin = build_application_style(&theme, &mut fallbacks).await;
+    root_ui().pop_skin();
+    root_ui().push_skin(&skin);
+    theme::set_palette(theme.palette);
+
+    (!fallbacks.is_empty()).then(|| {
+        format!(
+            "Couldn't load {}, using the built-in ones instead.",
+            fallbacks.join(", ")
+        )
+    })
+}
+
+/// Loads an image, or the built-in one if it can't be loaded
+async fn load_image_or(path: &str, fallback: &[u8], fallbacks: &mut Vec<String>) -> Image {
+    match load_image(path).await {
+        Ok(image) => image,
+        Err(err) => {
+            fallbacks.push(format!("{path} ({err})"));
+            Image::from_file_with_format(fallback, None).expect("built-in image is valid")
+        }
+    }
+}
+
+/// Loads a font's bytes, or the built-in font if it can't be loaded or isn't a font
+async fn load_font_or(path: &str, fallback: &[u8], fallbacks: &mut Vec<String>) -> Vec<u8> {
+    let font = load_file(path).await.and_then(|bytes| {
+        load_ttf_font_from_bytes(&bytes)?;
+        Ok(bytes)
+    });
+    font.unwrap_or_else(|err| {
+        fallbacks.push(format!("{path} ({err})"));
+        fallback.to_vec()
+    })
+}
@@ -0,0 +1,176 @@
+//! Vehicle Toolkit
+//!
+//! Themes, the look of the app described by a JSON file in `data/themes`.
+//!
+//! A theme names the images and font the ui skin is built from, the size and colour of its
+//! text, and the palette scenes draw with. Anything a theme leaves out is taken from the
+//! built-in theme, so a theme file only needs what it changes.
+use std::{
+    cell::Cell,
+    fs::{self, File},
+    io,
+    path::PathBuf,
+};
+
+use macroquad::prelude::{
+    BLACK, BLUE, Color, DARKGRAY, GRAY, LIGHTGRAY, ORANGE, RED, SKYBLUE, WHITE,
+};
+use serde::{Deserialize, Serialize};
+
+/// Folder themes are read from, a theme's name is its file name
+pub const THEMES_PATH: &str = "data/themes";
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Color")]
+struct ColorDef {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+/// Colours scenes draw with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Palette {
+    #[serde(with = "ColorDef")]
+    pub background: Color,
+    #[serde(with = "ColorDef")]
+    pub text: Color,
+    /// Behind title bars, legends and every other row of lists
+    #[serde(with = "ColorDef")]
+    pub panel: Color,
+    /// Marks what is being pointed at or changed, like the spawn point or a row being rebound
+    #[serde(with = "ColorDef")]
+    pub highlight: Color,
+    /// Text about something going wrong, like clashing bindings or locked hinges
+    #[serde(with = "ColorDef")]
+    pub warning: Color,
+    /* Editor */
+    #[serde(with = "ColorDef")]
+    pub select: Color,
+    /// Parts of the craft which can move freely, shown by the rigidity overlay
+    #[serde(with = "ColorDef")]
+    pub mechanism: Color,
+    #[serde(with = "ColorDef")]
+    pub brace: Color,
+    /* Crafts */
+    #[serde(with = "ColorDef")]
+    pub node: Color,
+    #[serde(with = "ColorDef")]
+    pub solid: Color,
+    #[serde(with = "ColorDef")]
+    pub rope: Color,
+    #[serde(with = "ColorDef")]
+    pub spring: Color,
+    #[serde(with = "ColorDef")]
+    pub piston: Color,
+    #[serde(with = "ColorDef")]
+    pub hinge: Color,
+    /// Winches and hinge motors
+    #[serde(with = "ColorDef")]
+    pub winch: Color,
+}
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            background: WHITE,
+            text: DARKGRAY,
+            panel: LIGHTGRAY,
+            highlight: SKYBLUE,
+            warning: RED,
+            select: Color::from_hex(0x1ffdff),
+            mechanism: Color::from_hex(0xff8c1f),
+            brace: Color::from_hex(0x3fbf3f),
+            node: BLACK,
+            solid: DARKGRAY,
+            rope: RED,
+            spring: BLUE,
+            piston: ORANGE,
+            hinge: GRAY,
+            winch: ORANGE,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Theme {
+    /* Skin assets */
+    pub window_background: String,
+    pub button_background: String,
+    pub button_clicked_background: String,
+    pub button_hovered_background: String,
+    pub font: String,
+    pub font_size: u16,
+    #[serde(with = "ColorDef")]
+    pub text_color: Color,
+
+    pub palette: Palette,
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            window_background: "assets/ui/window_bg.png".to_string(),
+            button_background: "assets/ui/button_bg.png".to_string(),
+            button_clicked_background: "assets/ui/button_clicked_bg.png".to_string(),
+            button_hovered_background: "assets/ui/button_hovered_bg.png".to_string(),
+            font: "assets/ui/fonts/ShareTech-Regular.ttf".to_string(),
+            font_size: 32,
+            text_color: WHITE,
+            palette: Palette::default(),
+        }
+    }
+}
+impl Theme {
+    /// Loads a theme from a JSON file
+    pub fn load(file_path: PathBuf) -> io::Result<Theme> {
+        let file = File::open(file_path)?;
+        serde_json::from_reader(file).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "JSON theme format seems to be invalid",
+            )
+        })
+    }
+
+    /// Loads the theme called `name` from the themes folder,
+    /// the built-in theme if there is no name or it can't be loaded, along with why
+    pub fn named(name: Option<&str>) -> (Theme, Option<String>) {
+        let Some(name) = name else {
+            return (Theme::default(), None);
+        };
+        match Theme::load(format!("{THEMES_PATH}/{name}.json").into()) {
+            Ok(theme) => (theme, None),
+            Err(err) => (Theme::default(), Some(format!("theme {name} ({err})"))),
+        }
+    }
+}
+
+/// Names of the themes in the themes folder, sorted
+pub fn theme_names() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(THEMES_PATH) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+thread_local! {
+    /// Palette of the current theme, read by scenes as they draw
+    static PALETTE: Cell<Palette> = Cell::new(Palette::default());
+}
+
+/// Colours of the current theme
+pub fn palette() -> Palette {
+    PALETTE.get()
+}
+
+pub(super) fn set_palette(palette: Palette) {
+    PALETTE.set(palette);
+}
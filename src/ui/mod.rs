@@ -1 +1,7 @@
-pub mod popups;
\ No newline at end of file
+//! Vehicle Toolkit
+//!
+//! Reusable pieces of user interface drawn with macroquad's ui and the application skin
+pub mod modal;
+pub mod widgets;
+
+pub use widgets::{Panel, Widget};
@@ -0,0 +1,170 @@
+//! Vehicle Toolkit
+//!
+//! Modal dialogs, shown over the current scene and blocking its input until answered.
+//!
+//! The application keeps a stack of dialogs and only the top one takes input. When it is
+//! answered the answer is handed back, along with the dialog's purpose, to whoever asked,
+//! usually the current scene. Escape cancels and Enter confirms the top dialog, as do the menu
+//! back and select actions so a dialog can be answered with a gamepad. Prompts leave those to
+//! the keyboard, as their keys may be needed for typing.
+use macroquad::{
+    prelude::{KeyCode, is_key_pressed, screen_height, screen_width, vec2},
+    ui::{hash, root_ui, widgets::InputText},
+};
+
+use crate::settings::{Action, KeyBinds};
+
+/// What a dialog was opened for, so its answer can be acted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Purpose {
+    /// Asked by the application before shutting down
+    Quit,
+    SaveCraftAs,
+    /// Asked before Save As replaces a craft which is already saved under that name
+    OverwriteCraft,
+    /// Asked before an edited craft is thrown away for a new one
+    NewCraft,
+    /// Offered at startup when autosaved work was left behind
+    Recover,
+    /// Only tells the user something, nothing happens when it is answered
+    Notice,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    Confirmed,
+    /// The second choice of a confirm dialog which has one
+    Denied,
+    Cancelled,
+    /// Text entered into a prompt
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogBody {
+    Confirm {
+        message: String,
+        confirm: String,
+        deny: Option<String>,
+    },
+    Prompt {
+        label: String,
+        value: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialog {
+    pub purpose: Purpose,
+    pub title: String,
+    pub body: DialogBody,
+}
+impl Dialog {
+    /// Asks a yes or no question
+    pub fn confirm(purpose: Purpose, title: &str, message: &str, confirm: &str) -> Self {
+        Self {
+            purpose,
+            title: title.to_string(),
+            body: DialogBody::Confirm {
+                message: message.to_string(),
+                confirm: confirm.to_string(),
+                deny: None,
+            },
+        }
+    }
+    /// Adds a second choice to a confirm dialog, answered with `Answer::Denied`
+    pub fn or_deny(mut self, deny: &str) -> Self {
+        if let DialogBody::Confirm { deny: d, .. } = &mut self.body {
+            *d = Some(deny.to_string());
+        }
+        self
+    }
+    /// Asks for a line of text
+    pub fn prompt(purpose: Purpose, title: &str, label: &str, value: &str) -> Self {
+        Self {
+            purpose,
+            title: title.to_string(),
+            body: DialogBody::Prompt {
+                label: label.to_string(),
+                value: value.to_string(),
+            },
+        }
+    }
+    pub fn error(title: &str, message: &str) -> Self {
+        Self {
+            purpose: Purpose::Notice,
+            title: title.to_string(),
+            body: DialogBody::Error {
+                message: message.to_string(),
+            },
+        }
+    }
+
+    /// Shows the dialog for this frame, returns the answer once it has one.
+    /// `key_binds` should be in the menu context
+    pub fn show(&mut self, depth: usize, key_binds: &KeyBinds) -> Option<Answer> {
+        let window_size = vec2(500.0, 260.0);
+        // Stacked dialogs are offset so the ones underneath stay visible
+        let window_pos = vec2(
+            screen_width() / 2.0 - window_size.x / 2.0 + depth as f32 * 20.0,
+            screen_height() / 2.0 - window_size.y / 2.0 + depth as f32 * 20.0,
+        );
+
+        let menu_keys = !matches!(self.body, DialogBody::Prompt { .. });
+        let mut answer = None;
+        if is_key_pressed(KeyCode::Escape)
+            || (menu_keys && key_binds.is_key_pressed(Action::MenuBack))
+        {
+            answer = Some(Answer::Cancelled);
+        }
+        let submitted = is_key_pressed(KeyCode::Enter)
+            || (menu_keys && key_binds.is_key_pressed(Action::MenuSelect));
+
+        root_ui().window(hash!("modal", depth), window_pos, window_size, |ui| {
+            ui.label(vec2(20.0, 10.0), &self.title);
+            match &mut self.body {
+                DialogBody::Confirm {
+                    message,
+                    confirm,
+                    deny,
+                } => {
+                    ui.label(vec2(20.0, 50.0), message);
+                    if ui.button(vec2(20.0, 130.0), "Cancel") {
+                        answer = Some(Answer::Cancelled);
+                    }
+                    if let Some(deny) = deny
+                        && ui.button(vec2(150.0, 130.0), deny.as_str())
+                    {
+                        answer = Some(Answer::Denied);
+                    }
+                    if ui.button(vec2(330.0, 130.0), confirm.as_str()) || submitted {
+                        answer = Some(Answer::Confirmed);
+                    }
+                }
+                DialogBody::Prompt { label, value } => {
+                    InputText::new(hash!("modal_input", depth))
+                        .label(label)
+                        .position(vec2(20.0, 60.0))
+                        .size(vec2(440.0, 36.0))
+                        .ui(ui, value);
+                    if ui.button(vec2(20.0, 130.0), "Cancel") {
+                        answer = Some(Answer::Cancelled);
+                    }
+                    if (ui.button(vec2(330.0, 130.0), "OK") || submitted) && !value.is_empty() {
+                        answer = Some(Answer::Text(value.clone()));
+                    }
+                }
+                DialogBody::Error { message } => {
+                    ui.label(vec2(20.0, 50.0), message);
+                    if ui.button(vec2(330.0, 130.0), "OK") || submitted {
+                        answer = Some(Answer::Confirmed);
+                    }
+                }
+            }
+        });
+        answer
+    }
+}
@@ -0,0 +1,371 @@
+//! Vehicle Toolkit
+//!
+//! Widgets described in `UI.md`, built up into panels declaratively.
+//!
+//! A panel is a list of widgets laid out top to bottom in a window. Widgets read from and
+//! write to a state passed in when the panel is shown, through the closures they are built
+//! with, so the same panel can drive an inspector, a settings page or a HUD.
+//! Inputs keep their own value between frames, which is why panels are built once and kept.
+use std::ops::Range;
+
+use macroquad::{
+    prelude::{
+        Color, GRAY, KeyCode, LIGHTGRAY, MouseButton, Rect, Vec2, is_key_pressed,
+        is_mouse_button_pressed, vec2,
+    },
+    ui::{Id, Ui, root_ui, widgets::InputText},
+};
+
+/// Width of a progress bar in pixels
+const BAR_WIDTH: f32 = 200.0;
+const BAR_HEIGHT: f32 = 18.0;
+const BAR_COLOR: Color = Color::new(0.3, 0.7, 0.4, 1.0);
+/// Fraction of the way a progress bar moves to its new value each frame
+const BAR_SMOOTHING: f32 = 0.2;
+
+/// Reads something to display from the state
+type Getter<S, T> = Box<dyn Fn(&S) -> T>;
+/// Writes a new value from an input into the state
+type Handler<S, T> = Box<dyn Fn(&mut S, T)>;
+type TextHandler<S> = Box<dyn Fn(&mut S, &str)>;
+
+pub enum Widget<S> {
+    /* Static */
+    Text {
+        text: String,
+    },
+    Break,
+
+    /* Displays */
+    DisplayText {
+        label: String,
+        get_text: Getter<S, String>,
+    },
+    DisplayNumber {
+        label: String,
+        get_number: Getter<S, f32>,
+    },
+    /// Fills up to a value within `0.0..=1.0`, easing towards it rather than jumping
+    ProgressBar {
+        label: String,
+        last_value: f32,
+        get_value: Getter<S, f32>,
+    },
+
+    /* Inputs */
+    Button {
+        label: String,
+        on_press: Box<dyn Fn(&mut S)>,
+    },
+    /// Submitted when Enter is pressed after editing, holding at most `max_len` characters
+    TextInput {
+        label: String,
+        max_len: usize,
+        value: String,
+        edited: bool,
+        on_submit: TextHandler<S>,
+    },
+    /// Submitted when Enter is pressed after editing, clamped between `min` and `max`
+    NumberInput {
+        label: String,
+        max: f32,
+        min: f32,
+        value: f32,
+        text: String,
+        edited: bool,
+        on_submit: Handler<S, f32>,
+    },
+    Slider {
+        label: String,
+        max: f32,
+        min: f32,
+        value: f32,
+        on_change: Handler<S, f32>,
+    },
+    Toggle {
+        label: String,
+        state: bool,
+        on_toggle: Handler<S, bool>,
+    },
+}
+impl<S> Widget<S> {
+    /* Building widgets */
+    pub fn text(text: &str) -> Self {
+        Widget::Text {
+            text: text.to_string(),
+        }
+    }
+    pub fn display_text(label: &str, get_text: impl Fn(&S) -> String + 'static) -> Self {
+        Widget::DisplayText {
+            label: label.to_string(),
+            get_text: Box::new(get_text),
+        }
+    }
+    pub fn display_number(label: &str, get_number: impl Fn(&S) -> f32 + 'static) -> Self {
+        Widget::DisplayNumber {
+            label: label.to_string(),
+            get_number: Box::new(get_number),
+        }
+    }
+    pub fn progress_bar(label: &str, get_value: impl Fn(&S) -> f32 + 'static) -> Self {
+        Widget::ProgressBar {
+            label: label.to_string(),
+            last_value: 0.0,
+            get_value: Box::new(get_value),
+        }
+    }
+    pub fn button(label: &str, on_press: impl Fn(&mut S) + 'static) -> Self {
+        Widget::Button {
+            label: label.to_string(),
+            on_press: Box::new(on_press),
+        }
+    }
+    pub fn text_input(
+        label: &str,
+        max_len: usize,
+        value: &str,
+        on_submit: impl Fn(&mut S, &str) + 'static,
+    ) -> Self {
+        Widget::TextInput {
+            label: label.to_string(),
+            max_len,
+            value: value.to_string(),
+            edited: false,
+            on_submit: Box::new(on_submit),
+        }
+    }
+    pub fn number_input(
+        label: &str,
+        range: Range<f32>,
+        value: f32,
+        on_submit: impl Fn(&mut S, f32) + 'static,
+    ) -> Self {
+        let (min, max) = sorted(range);
+        Widget::NumberInput {
+            label: label.to_string(),
+            max,
+            min,
+            value,
+            text: value.to_string(),
+            edited: false,
+            on_submit: Box::new(on_submit),
+        }
+    }
+    pub fn slider(
+        label: &str,
+        range: Range<f32>,
+        value: f32,
+        on_change: impl Fn(&mut S, f32) + 'static,
+    ) -> Self {
+        let (min, max) = sorted(range);
+        Widget::Slider {
+            label: label.to_string(),
+            max,
+            min,
+            value,
+            on_change: Box::new(on_change),
+        }
+    }
+    pub fn toggle(label: &str, state: bool, on_toggle: impl Fn(&mut S, bool) + 'static) -> Self {
+        Widget::Toggle {
+            label: label.to_string(),
+            state,
+            on_toggle: Box::new(on_toggle),
+        }
+    }
+
+    /// Lays out the widget below the previous one and handles its input.
+    /// Enter only submits an input if it has the keyboard `focus`, or is typed into this frame.
+    /// Returns whether the widget was typed into
+    fn ui(&mut self, ui: &mut Ui, id: Id, state: &mut S, focus: bool) -> bool {
+        let submitted = is_key_pressed(KeyCode::Enter);
+        let mut typed = false;
+        match self {
+            Widget::Text { text } => ui.label(None, text),
+            Widget::Break => ui.separator(),
+            Widget::DisplayText { label, get_text } => {
+                ui.label(None, &format!("{label}: {}", get_text(state)))
+            }
+            Widget::DisplayNumber { label, get_number } => {
+                ui.label(None, &format!("{label}: {:.2}", get_number(state)))
+            }
+            Widget::ProgressBar {
+                label,
+                last_value,
+                get_value,
+            } => {
+                let target = get_value(state).clamp(0.0, 1.0);
+                *last_value += (target - *last_value) * BAR_SMOOTHING;
+
+                ui.label(None, label);
+                let mut canvas = ui.canvas();
+                let pos: Vec2 = canvas.request_space(vec2(BAR_WIDTH, BAR_HEIGHT));
+                let full = Rect::new(pos.x, pos.y, BAR_WIDTH, BAR_HEIGHT);
+                let filled = Rect::new(pos.x, pos.y, BAR_WIDTH * *last_value, BAR_HEIGHT);
+                canvas.rect(full, GRAY, LIGHTGRAY);
+                canvas.rect(filled, None, BAR_COLOR);
+            }
+            Widget::Button { label, on_press } => {
+                if ui.button(None, label.as_str()) {
+                    on_press(state);
+                }
+            }
+            Widget::TextInput {
+                label,
+                max_len,
+                value,
+                edited,
+                on_submit,
+            } => {
+                let before = value.clone();
+                InputText::new(id).label(label).ui(ui, value);
+                truncate_chars(value, *max_len);
+                typed = *value != before;
+                *edited |= typed;
+                if *edited && submitted && (focus || typed) {
+                    *edited = false;
+                    on_submit(state, value);
+                }
+            }
+            Widget::NumberInput {
+                label,
+                max,
+                min,
+                value,
+                text,
+                edited,
+                on_submit,
+            } => {
+                let before = text.clone();
+                InputText::new(id)
+                    .label(label)
+                    .filter_numbers()
+                    .ui(ui, text);
+                typed = *text != before;
+                *edited |= typed;
+                if *edited && submitted && (focus || typed) {
+                    *edited = false;
+                    if let Ok(number) = text.parse::<f32>() {
+                        *value = number.clamp(*min, *max);
+                        on_submit(state, *value);
+                    }
+                    *text = value.to_string();
+                }
+            }
+            Widget::Slider {
+                label,
+                max,
+                min,
+                value,
+                on_change,
+            } => {
+                let before = *value;
+                ui.slider(id, label, *min..*max, value);
+                if *value != before {
+                    on_change(state, *value);
+                }
+            }
+            Widget::Toggle {
+                label,
+                state: on,
+                on_toggle,
+            } => {
+                let before = *on;
+                ui.checkbox(id, label, on);
+                if *on != before {
+                    on_toggle(state, *on);
+                }
+            }
+        }
+        typed
+    }
+}
+
+/// Cuts text down to `max_len` characters, `String::truncate` counts bytes instead
+fn truncate_chars(text: &mut String, max_len: usize) {
+    if let Some((end, _)) = text.char_indices().nth(max_len) {
+        text.truncate(end);
+    }
+}
+
+/// Bounds of a range, smallest first, so a range given the wrong way round still works
+fn sorted(range: Range<f32>) -> (f32, f32) {
+    (range.start.min(range.end), range.start.max(range.end))
+}
+
+/// Widgets shown together in a window, one below the other
+pub struct Panel<S> {
+    title: Option<String>,
+    widgets: Vec<Widget<S>>,
+    /// Input last typed into, which has the keyboard focus unless something was clicked since
+    focus: Option<usize>,
+}
+impl<S> Default for Panel<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<S> Panel<S> {
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            widgets: vec![],
+            focus: None,
+        }
+    }
+    pub fn titled(title: &str) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            widgets: vec![],
+            focus: None,
+        }
+    }
+
+    /// Adds a widget below the others
+    pub fn with(mut self, widget: Widget<S>) -> Self {
+        self.widgets.push(widget);
+        self
+    }
+    pub fn push(&mut self, widget: Widget<S>) {
+        self.widgets.push(widget);
+    }
+
+    /// Shows the panel in a window for this frame, `id` has to be unique to the panel
+    pub fn show(&mut self, id: Id, pos: Vec2, size: Vec2, state: &mut S) {
+        // A click moves the focus somewhere unknown, it is found again once an input is typed in
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.focus = None;
+        }
+        root_ui().window(id, pos, size, |ui| {
+            if let Some(title) = &self.title {
+                ui.label(None, title);
+            }
+            for (i, widget) in self.widgets.iter_mut().enumerate() {
+                let focus = self.focus == Some(i);
+                if widget.ui(ui, id.wrapping_add(i as Id + 1), state, focus) {
+                    self.focus = Some(i);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_cut_at_characters_not_bytes() {
+        let mut text = "héllo wörld".to_string();
+        truncate_chars(&mut text, 8);
+        assert_eq!(text, "héllo wö");
+        truncate_chars(&mut text, 20);
+        assert_eq!(text, "héllo wö");
+    }
+
+    #[test]
+    fn reversed_ranges_are_sorted() {
+        assert_eq!(sorted(10.0..-5.0), (-5.0, 10.0));
+        assert_eq!(sorted(0.0..1.0), (0.0, 1.0));
+    }
+}